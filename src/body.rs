@@ -0,0 +1,503 @@
+use crate::ctx::Context;
+use crate::error::{Error, Reporter};
+
+use regex::Regex;
+use std::ops::Range;
+
+pub const ABSTRACT_MAX_WORDS: usize = 200;
+
+/// Matches an `<!-- eipv-disable code, ... -->` comment, capturing the codes.
+pub(crate) const DISABLE_COMMENT: &str = r"<!--\s*eipv-disable\s+([^>]*?)\s*-->";
+
+/// The sections an EIP may contain, in the order they must appear, along with
+/// whether they are required.
+const SECTIONS: &[(&str, bool)] = &[
+    ("Abstract", true),
+    ("Motivation", false),
+    ("Specification", true),
+    ("Rationale", true),
+    ("Backwards Compatibility", false),
+    ("Test Cases", false),
+    ("Implementation", false),
+    ("Reference Implementation", false),
+    ("Security Considerations", true),
+    // presence is checked along with the waiver itself
+    ("Copyright", false),
+];
+
+/// The accepted copyright waivers, with and without a link to the license.
+const COPYRIGHT_WAIVERS: &[&str] = &[
+    "Copyright and related rights waived via CC0.",
+    "Copyright and related rights waived via [CC0](https://creativecommons.org/publicdomain/zero/1.0/).",
+];
+
+/// A top level (`##`) section of the body.
+#[derive(Debug)]
+pub struct Section<'a> {
+    pub title: &'a str,
+    /// Byte range of the heading line, relative to the body.
+    pub heading: Range<usize>,
+    /// Byte range of everything up to the next section, relative to the body.
+    pub content: Range<usize>,
+}
+
+/// A link in the body.
+#[derive(Debug)]
+pub struct Link<'a> {
+    pub url: &'a str,
+    /// Byte range of the URL, relative to the body.
+    pub range: Range<usize>,
+}
+
+/// Splits the body into its top level sections. Headings inside fenced code
+/// blocks are ignored.
+pub fn sections(body: &str) -> Vec<Section<'_>> {
+    let mut sections: Vec<Section> = vec![];
+
+    for (start, line) in unfenced_lines(body) {
+        if let Some(title) = line.strip_prefix("## ") {
+            if let Some(last) = sections.last_mut() {
+                last.content.end = start;
+            }
+
+            let title = title.trim().trim_end_matches('#').trim_end();
+            let end = start + line.trim_end().len();
+
+            sections.push(Section {
+                title,
+                heading: start..end,
+                content: start + line.len()..start + line.len(),
+            });
+        }
+    }
+
+    if let Some(last) = sections.last_mut() {
+        last.content.end = body.len();
+    }
+
+    sections
+}
+
+/// Finds the destination of every inline link, autolink and link reference
+/// definition in the body. Links inside code are ignored.
+pub fn links(body: &str) -> Vec<Link<'_>> {
+    let mut links = vec![];
+
+    let mut push = |start: usize, len: usize| {
+        if len != 0 {
+            links.push(Link {
+                url: &body[start..start + len],
+                range: start..start + len,
+            });
+        }
+    };
+
+    for part in outside_code(body) {
+        let start = part.start;
+        let line = &body[part];
+
+        // link reference definition, e.g. `[1]: https://example.com`
+        let line_start = start == 0 || body[..start].ends_with('\n');
+        if let Some(rest) = line.trim_start().strip_prefix('[').filter(|_| line_start) {
+            if let Some(i) = rest.find("]:") {
+                let dest = &rest[i + 2..];
+                let url = dest.split_whitespace().next().unwrap_or("");
+                let pos = line.len() - dest.len() + (dest.len() - dest.trim_start().len());
+                push(start + pos, url.len());
+                continue;
+            }
+        }
+
+        let bytes = line.as_bytes();
+        let mut i = 0;
+
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => i += 1,
+                b']' if bytes.get(i + 1) == Some(&b'(') => {
+                    // the destination ends at the closing parenthesis or the
+                    // start of a title
+                    let dest = &line[i + 2..];
+                    let len = dest
+                        .find(|c: char| c == ')' || c.is_whitespace())
+                        .unwrap_or(dest.len());
+                    push(start + i + 2, len);
+                    i += 2 + len;
+                    continue;
+                }
+                b'<' => {
+                    let dest = &line[i + 1..];
+                    if let Some(len) = dest.find('>') {
+                        if dest[..len].contains("://") && !dest[..len].contains(' ') {
+                            push(start + i + 1, len);
+                            i += 2 + len;
+                            continue;
+                        }
+                    }
+                }
+                _ => (),
+            }
+
+            i += 1;
+        }
+    }
+
+    links
+}
+
+/// Validates the body of an EIP. `offset` is the position of the body within
+/// the EIP.
+pub(crate) fn validate(ctx: &Context, body: &str, offset: usize, errors: &mut Reporter) {
+    let sections = sections(body);
+
+    // disable comments may go anywhere, so they're blanked out of the checks
+    // which look at the content of sections
+    let content = Regex::new(DISABLE_COMMENT)
+        .unwrap()
+        .replace_all(body, |c: &regex::Captures| " ".repeat(c[0].len()));
+
+    validate_sections(&sections, offset, errors);
+    validate_abstract(ctx, &content, &sections, offset, errors);
+    validate_copyright(&content, &sections, offset, errors);
+    validate_brackets(body, offset, errors);
+    validate_links(body, offset, errors);
+}
+
+/// Checks that the required sections are present, and that every section is
+/// known, unique and in order.
+fn validate_sections(sections: &[Section], offset: usize, errors: &mut Reporter) {
+    // the highest position in `SECTIONS` seen so far
+    let mut last: Option<usize> = None;
+
+    for (i, section) in sections.iter().enumerate() {
+        let range = offset + section.heading.start..offset + section.heading.end;
+
+        let position = match SECTIONS.iter().position(|(t, _)| *t == section.title) {
+            Some(p) => p,
+            None => {
+                errors.report(Error::UnknownSection(section.title.to_string()), range);
+                continue;
+            }
+        };
+
+        if sections[..i].iter().any(|s| s.title == section.title) {
+            errors.report(Error::DuplicateSection(section.title.to_string()), range);
+            continue;
+        }
+
+        match last {
+            Some(l) if position < l => {
+                errors.report(Error::OutOfOrderSection(section.title.to_string()), range)
+            }
+            _ => last = Some(position),
+        }
+    }
+
+    for (title, _) in SECTIONS.iter().filter(|(_, required)| *required) {
+        if !sections.iter().any(|s| s.title == *title) {
+            errors.report(Error::MissingSection(title.to_string()), offset..offset);
+        }
+    }
+}
+
+fn validate_abstract(
+    ctx: &Context,
+    body: &str,
+    sections: &[Section],
+    offset: usize,
+    errors: &mut Reporter,
+) {
+    if let Some(section) = sections.iter().find(|s| s.title == "Abstract") {
+        let words = count_words(&body[section.content.clone()]);
+        let max = ctx.abstract_max_words();
+
+        if max < words {
+            errors.report(
+                Error::AbstractTooLong { words, max },
+                offset + section.heading.start..offset + section.heading.end,
+            );
+        }
+    }
+}
+
+/// Checks that the last section is the copyright waiver, and that it
+/// contains only one of the accepted waivers.
+fn validate_copyright(body: &str, sections: &[Section], offset: usize, errors: &mut Reporter) {
+    let (i, section) = match sections
+        .iter()
+        .enumerate()
+        .find(|(_, s)| s.title == "Copyright")
+    {
+        Some(v) => v,
+        None => {
+            errors.report(
+                Error::MissingCopyrightWaiver,
+                offset + body.len()..offset + body.len(),
+            );
+            return;
+        }
+    };
+
+    let content = &body[section.content.clone()];
+    let waiver = content.trim();
+    let start = offset + section.content.start + content.len() - content.trim_start().len();
+    let range = start..start + waiver.len();
+
+    if !COPYRIGHT_WAIVERS.contains(&waiver) {
+        match COPYRIGHT_WAIVERS.iter().find(|w| waiver.starts_with(*w)) {
+            Some(w) => errors.report(
+                Error::ContentAfterCopyrightWaiver,
+                start + w.len()..range.end,
+            ),
+            None => errors.report(Error::ModifiedCopyrightWaiver, range),
+        }
+    }
+
+    if let Some(next) = sections.get(i + 1) {
+        errors.report(
+            Error::ContentAfterCopyrightWaiver,
+            offset + next.heading.start..offset + body.len(),
+        );
+    }
+}
+
+/// Reports every `(`, `[` and `{` which is never closed. Fenced code blocks,
+/// inline code spans and math are skipped.
+fn validate_brackets(body: &str, offset: usize, errors: &mut Reporter) {
+    let mut stack: Vec<(char, usize)> = vec![];
+    let mut display_math = false;
+
+    for part in outside_code(body) {
+        let start = part.start;
+        let line = &body[part];
+        let bytes = line.as_bytes();
+        let mut i = 0;
+
+        while i < bytes.len() {
+            match bytes[i] {
+                b'$' if bytes.get(i + 1) == Some(&b'$') => {
+                    display_math = !display_math;
+                    i += 2;
+                    continue;
+                }
+                _ if display_math => (),
+                b'\\' => i += 1,
+                b'$' => {
+                    if let Some(end) = line[i + 1..].find('$') {
+                        i += end + 2;
+                        continue;
+                    }
+                }
+                c @ (b'(' | b'[' | b'{') => stack.push((c as char, start + i)),
+                c @ (b')' | b']' | b'}') => {
+                    let open = match c {
+                        b')' => '(',
+                        b']' => '[',
+                        _ => '{',
+                    };
+
+                    // anything opened after the matching bracket is unclosed
+                    if let Some(pos) = stack.iter().rposition(|(o, _)| *o == open) {
+                        for (o, p) in stack.drain(pos..).skip(1) {
+                            errors.report(Error::UnmatchedBracket(o), offset + p..offset + p + 1);
+                        }
+                    }
+                }
+                _ => (),
+            }
+
+            i += 1;
+        }
+    }
+
+    for (o, p) in stack {
+        errors.report(Error::UnmatchedBracket(o), offset + p..offset + p + 1);
+    }
+}
+
+/// Reports absolute links to EIPs, whether on the EIPs website or GitHub, as
+/// they should be relative links to the file instead. The suggested file is
+/// named the way those sites name it, whatever the configured pattern.
+fn validate_links(body: &str, offset: usize, errors: &mut Reporter) {
+    let re = Regex::new(
+        r"^https?://(?:eips\.ethereum\.org/EIPS/eip-(\d+)(?:\.html)?|github\.com/ethereum/EIPs/blob/[^/]+/EIPS/eip-(\d+)\.md)/?(#.*)?$",
+    )
+    .unwrap();
+
+    for link in links(body) {
+        if let Some(c) = re.captures(link.url) {
+            let n = c.get(1).or_else(|| c.get(2)).unwrap().as_str();
+            let fragment = c.get(3).map(|m| m.as_str()).unwrap_or("");
+            let relative = format!("./eip-{}.md{}", n, fragment);
+
+            errors.report(
+                Error::AbsoluteEipLink(relative),
+                offset + link.range.start..offset + link.range.end,
+            );
+        }
+    }
+}
+
+/// Counts the words of prose in `s`, skipping code and the URLs of links.
+fn count_words(s: &str) -> usize {
+    let mut prose = String::new();
+
+    for part in outside_code(s) {
+        let mut chars = s[part].chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                // link destination, only the link text is counted
+                ']' if chars.peek() == Some(&'(') => {
+                    for c in chars.by_ref() {
+                        if c == ')' {
+                            break;
+                        }
+                    }
+                    prose.push(' ');
+                }
+                _ => prose.push(c),
+            }
+        }
+
+        // code spans separate words
+        prose.push(' ');
+    }
+
+    prose
+        .split_whitespace()
+        .filter(|w| w.chars().any(|c| c.is_alphanumeric()))
+        .count()
+}
+
+/// Returns the fence opening a code block on `line`, e.g. "````", if any.
+fn opening_fence(line: &str) -> Option<&str> {
+    let trimmed = line.trim();
+    let c = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.len() - trimmed.trim_start_matches(c).len();
+
+    // the info string of a backtick fence can't contain backticks
+    match len >= 3 && !(c == '`' && trimmed[len..].contains('`')) {
+        true => Some(&trimmed[..len]),
+        false => None,
+    }
+}
+
+/// Returns whether `line` closes the code block opened by `fence`, which it
+/// does with a run of the same character at least as long.
+fn closes_fence(line: &str, fence: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.len() >= fence.len() && trimmed.chars().all(|c| fence.starts_with(c))
+}
+
+/// Returns every line of `s` outside fenced code blocks, newline included,
+/// along with its offset. The fences themselves are left out.
+fn unfenced_lines(s: &str) -> Vec<(usize, &str)> {
+    let mut lines = vec![];
+    let mut fence: Option<&str> = None;
+    let mut offset = 0;
+
+    for line in s.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+
+        match fence {
+            Some(f) if closes_fence(line, f) => fence = None,
+            Some(_) => (),
+            None => match opening_fence(line) {
+                Some(f) => fence = Some(f),
+                None => lines.push((start, line)),
+            },
+        }
+    }
+
+    lines
+}
+
+/// Returns the ranges of `s` outside code, i.e. fenced code blocks and inline
+/// code spans. A range never spans more than one line.
+fn outside_code(s: &str) -> Vec<Range<usize>> {
+    let mut parts = vec![];
+
+    let mut push = |range: Range<usize>| {
+        if !range.is_empty() {
+            parts.push(range);
+        }
+    };
+
+    for (start, line) in unfenced_lines(s) {
+        let bytes = line.as_bytes();
+        let mut from = 0;
+        let mut i = 0;
+
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => i += 2,
+                b'`' => {
+                    // a code span ends at the next run of exactly as many
+                    // backticks on the same line
+                    let run = bytes[i..].iter().take_while(|b| **b == b'`').count();
+                    match backtick_run(&line[i + run..], run) {
+                        Some(end) => {
+                            push(start + from..start + i);
+                            i += run + end + run;
+                            from = i;
+                        }
+                        None => i += run,
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+
+        push(start + from..start + line.len());
+    }
+
+    parts
+}
+
+/// Returns the position of the first run of exactly `len` backticks in `s`.
+fn backtick_run(s: &str, len: usize) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'`' => {
+                let run = bytes[i..].iter().take_while(|b| **b == b'`').count();
+                if run == len {
+                    return Some(i);
+                }
+                i += run;
+            }
+            _ => i += 1,
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_words_skips_code_and_urls() {
+        let s = "An `inline code span` and a [link](https://eips.ethereum.org/a/b).\n\n```\nfn main() {}\n```\n- item\n";
+        assert_eq!(count_words(s), 5);
+    }
+
+    #[test]
+    fn outside_code_skips_fences_and_spans() {
+        let s = "a\n````\n```\nb\n```\n````\n~~~\n```\n~~~\nc ``d ` e`` f\n";
+        let parts: Vec<&str> = outside_code(s).into_iter().map(|r| &s[r]).collect();
+        assert_eq!(parts, ["a\n", "c ", " f\n"]);
+    }
+
+    #[test]
+    fn links_skips_code() {
+        let s =
+            "[a](./eip-1.md) <https://example.com> `[b](c)`\n\n```\n[d](e)\n```\n[f]: ./eip-2.md\n";
+        let urls: Vec<&str> = links(s).iter().map(|l| l.url).collect();
+        assert_eq!(urls, ["./eip-1.md", "https://example.com", "./eip-2.md"]);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::ctx::Context;
+use crate::eip::{Category, Status};
+use crate::error::{Error, Severity};
+
+use anyhow::{anyhow, Context as _, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE_NAME: &str = "eipv.toml";
+
+/// Project configuration, read from an `eipv.toml` file. Every setting mirrors
+/// a command line flag, which takes precedence over it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub ignore: Vec<String>,
+    /// File names or globs, e.g. `eip-1*.md`.
+    pub skip: Vec<String>,
+    pub deny: Vec<String>,
+    pub warn: Vec<String>,
+    pub info: Vec<String>,
+    pub deny_warnings: bool,
+    pub title_max_length: Option<usize>,
+    pub description_max_length: Option<usize>,
+    pub abstract_max_words: Option<usize>,
+    pub statuses: Option<Vec<String>>,
+    pub categories: Option<Vec<String>>,
+    pub file_name_pattern: Option<String>,
+    pub discussions_to_host: Option<String>,
+    pub format: Option<String>,
+    /// Settings for the files matching each name or glob.
+    pub files: BTreeMap<String, FileConfig>,
+}
+
+/// Settings for specific files, read from a `[files."eip-1.md"]` table.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FileConfig {
+    pub ignore: Vec<String>,
+}
+
+impl Config {
+    pub fn from_str(s: &str) -> Result<Self> {
+        Ok(toml::from_str(s)?)
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path)?;
+        Self::from_str(&s).with_context(|| format!("invalid config {}", path.display()))
+    }
+
+    /// Looks for an `eipv.toml` next to `path`, then in each directory above
+    /// it, returning the first one found along with its location.
+    pub fn discover<P: AsRef<Path>>(path: P) -> Result<Option<(PathBuf, Self)>> {
+        let path = path.as_ref().canonicalize()?;
+        let dir = match path.is_file() {
+            true => path.parent(),
+            false => Some(path.as_path()),
+        };
+
+        for dir in dir.into_iter().flat_map(Path::ancestors) {
+            let candidate = dir.join(CONFIG_FILE_NAME);
+            if candidate.is_file() {
+                let config = Self::from_path(&candidate)?;
+                return Ok(Some((candidate, config)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Applies every setting to `ctx`, except for the output format.
+    pub fn apply(&self, ctx: &mut Context) -> Result<()> {
+        for code in self.ignore.iter() {
+            ctx.ignore(Error::from_str(code)?);
+        }
+
+        for (file, config) in self.files.iter() {
+            for code in config.ignore.iter() {
+                ctx.ignore_in(file, Error::from_str(code)?);
+            }
+        }
+
+        for s in self.skip.iter() {
+            ctx.skip(s);
+        }
+
+        for (codes, severity) in [
+            (&self.deny, Severity::Error),
+            (&self.warn, Severity::Warning),
+            (&self.info, Severity::Info),
+        ] {
+            for code in codes.iter() {
+                ctx.set_severity(Error::from_str(code)?, severity);
+            }
+        }
+
+        if self.deny_warnings {
+            ctx.deny_warnings();
+        }
+
+        if let Some(n) = self.title_max_length {
+            ctx.set_title_max_length(n);
+        }
+
+        if let Some(n) = self.description_max_length {
+            ctx.set_description_max_length(n);
+        }
+
+        if let Some(n) = self.abstract_max_words {
+            ctx.set_abstract_max_words(n);
+        }
+
+        if let Some(statuses) = &self.statuses {
+            let statuses = statuses
+                .iter()
+                .map(|s| Status::from_str(s).map_err(|_| anyhow!("unknown status: {}", s)))
+                .collect::<Result<_>>()?;
+            ctx.set_statuses(statuses);
+        }
+
+        if let Some(categories) = &self.categories {
+            let categories = categories
+                .iter()
+                .map(|c| Category::from_str(c).map_err(|_| anyhow!("unknown category: {}", c)))
+                .collect::<Result<_>>()?;
+            ctx.set_categories(categories);
+        }
+
+        if let Some(pattern) = &self.file_name_pattern {
+            ctx.set_file_name_pattern(pattern);
+        }
+
+        if let Some(host) = &self.discussions_to_host {
+            ctx.set_discussions_to_host(host);
+        }
+
+        Ok(())
+    }
+}
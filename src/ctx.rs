@@ -1,26 +1,200 @@
-use crate::error::Error;
-use std::collections::HashSet;
+use crate::body::{ABSTRACT_MAX_WORDS, DISABLE_COMMENT};
+use crate::eip::{Category, Status};
+use crate::error::{Diagnostic, Error, Severity};
+use crate::validators::{DESCRIPTION_MAX_LEN, FILE_NAME_PATTERN, TITLE_MAX_LEN};
 
-#[derive(Debug, Default)]
+use chrono::{NaiveDate, Utc};
+use glob::Pattern;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Debug, Default)]
 pub struct Context {
-    ignore: HashSet<Error>,
-    skip: HashSet<String>,
+    // errors are keyed by code, so variants carrying data match regardless
+    // of their contents
+    ignore: HashSet<&'static str>,
+    file_ignore: Vec<(Pattern, HashSet<&'static str>)>,
+    skip: Vec<Pattern>,
+    severity: HashMap<&'static str, Severity>,
+    deny_warnings: bool,
+    title_max_length: Option<usize>,
+    description_max_length: Option<usize>,
+    abstract_max_words: Option<usize>,
+    statuses: Option<Vec<Status>>,
+    categories: Option<Vec<Category>>,
+    file_name_pattern: Option<String>,
+    discussions_to_host: Option<String>,
+    today: Option<NaiveDate>,
 }
 
 impl Context {
+    /// Skips files whose name matches `s`, which may be a glob like
+    /// `eip-1*.md`.
     pub fn skip(&mut self, s: &str) {
-        self.skip.insert(s.to_string());
+        self.skip.push(pattern(s));
     }
 
     pub fn ignore(&mut self, e: Error) {
-        self.ignore.insert(e);
+        self.ignore.insert(e.code());
+    }
+
+    /// Ignores `e` only in files whose name matches `file`, which may be a
+    /// glob like `eip-1*.md`.
+    pub fn ignore_in(&mut self, file: &str, e: Error) {
+        match self
+            .file_ignore
+            .iter_mut()
+            .find(|(p, _)| p.as_str() == file)
+        {
+            Some((_, codes)) => {
+                codes.insert(e.code());
+            }
+            None => self
+                .file_ignore
+                .push((pattern(file), std::iter::once(e.code()).collect())),
+        }
+    }
+
+    /// Returns the context for the EIP `src`, which also ignores the errors
+    /// disabled by `<!-- eipv-disable code -->` comments within it.
+    pub fn with_source(&self, src: &str) -> Context {
+        let re = Regex::new(DISABLE_COMMENT).unwrap();
+
+        let mut ctx = self.clone();
+        for c in re.captures_iter(src) {
+            let codes = c[1].split(|c: char| c == ',' || c.is_whitespace());
+
+            // unknown codes are left alone, so the errors they were meant to
+            // disable are still reported
+            for e in codes.filter_map(|c| Error::from_str(c).ok()) {
+                ctx.ignore(e);
+            }
+        }
+
+        ctx
+    }
+
+    /// Returns the context for the file `name` containing `src`, which also
+    /// ignores the errors disabled for that file alone, whether in the config
+    /// or by comments within it.
+    pub fn for_file(&self, name: &str, src: &str) -> Context {
+        let mut ctx = self.with_source(src);
+        for (_, codes) in self.file_ignore.iter().filter(|(p, _)| p.matches(name)) {
+            ctx.ignore.extend(codes);
+        }
+
+        ctx
+    }
+
+    /// Overrides the default severity of `e`.
+    pub fn set_severity(&mut self, e: Error, s: Severity) {
+        self.severity.insert(e.code(), s);
+    }
+
+    /// Treat warnings as errors when deciding whether an EIP is valid.
+    pub fn deny_warnings(&mut self) {
+        self.deny_warnings = true;
+    }
+
+    pub fn set_title_max_length(&mut self, n: usize) {
+        self.title_max_length = Some(n);
+    }
+
+    pub fn set_description_max_length(&mut self, n: usize) {
+        self.description_max_length = Some(n);
+    }
+
+    pub fn set_abstract_max_words(&mut self, n: usize) {
+        self.abstract_max_words = Some(n);
+    }
+
+    /// Only accepts EIPs with one of `statuses`, instead of any status.
+    pub fn set_statuses(&mut self, statuses: Vec<Status>) {
+        self.statuses = Some(statuses);
+    }
+
+    /// Only accepts EIPs with one of `categories`, instead of any category.
+    pub fn set_categories(&mut self, categories: Vec<Category>) {
+        self.categories = Some(categories);
+    }
+
+    /// Sets the pattern EIP file names must follow, where `{}` stands for
+    /// the EIP number, e.g. `erc-{}.md`.
+    pub fn set_file_name_pattern(&mut self, pattern: &str) {
+        self.file_name_pattern = Some(pattern.to_string());
+    }
+
+    /// Requires `discussions-to` to be on `host`, e.g.
+    /// `ethereum-magicians.org`.
+    pub fn set_discussions_to_host(&mut self, host: &str) {
+        self.discussions_to_host = Some(host.to_string());
+    }
+
+    /// Fixes the date dates are compared against, instead of the current one.
+    pub fn set_today(&mut self, today: NaiveDate) {
+        self.today = Some(today);
     }
 
     pub fn should_ignore(&self, e: &Error) -> bool {
-        self.ignore.contains(e)
+        self.ignore.contains(e.code())
     }
 
     pub fn should_skip(&self, s: &str) -> bool {
-        self.skip.contains(s)
+        self.skip.iter().any(|p| p.matches(s))
+    }
+
+    pub fn severity(&self, e: &Error) -> Severity {
+        self.severity
+            .get(e.code())
+            .copied()
+            .unwrap_or_else(|| e.default_severity())
+    }
+
+    pub fn title_max_length(&self) -> usize {
+        self.title_max_length.unwrap_or(TITLE_MAX_LEN)
+    }
+
+    pub fn description_max_length(&self) -> usize {
+        self.description_max_length.unwrap_or(DESCRIPTION_MAX_LEN)
+    }
+
+    pub fn allows_status(&self, s: &Status) -> bool {
+        self.statuses.as_ref().is_none_or(|v| v.contains(s))
+    }
+
+    pub fn allows_category(&self, c: &Category) -> bool {
+        self.categories.as_ref().is_none_or(|v| v.contains(c))
+    }
+
+    pub fn abstract_max_words(&self) -> usize {
+        self.abstract_max_words.unwrap_or(ABSTRACT_MAX_WORDS)
+    }
+
+    pub fn file_name_pattern(&self) -> &str {
+        self.file_name_pattern
+            .as_deref()
+            .unwrap_or(FILE_NAME_PATTERN)
+    }
+
+    pub fn discussions_to_host(&self) -> Option<&str> {
+        self.discussions_to_host.as_deref()
     }
+
+    pub fn today(&self) -> NaiveDate {
+        self.today.unwrap_or_else(|| Utc::now().naive_utc().date())
+    }
+
+    /// Returns whether `d` makes the EIP it was reported against invalid.
+    pub fn fails(&self, d: &Diagnostic) -> bool {
+        match d.severity {
+            Severity::Error => true,
+            Severity::Warning => self.deny_warnings,
+            Severity::Info => false,
+        }
+    }
+}
+
+/// Parses the glob `s`, matching it literally if it isn't a valid one.
+fn pattern(s: &str) -> Pattern {
+    Pattern::new(s).unwrap_or_else(|_| Pattern::new(&Pattern::escape(s)).unwrap())
 }
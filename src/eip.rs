@@ -1,8 +1,10 @@
-use crate::error::Error;
-use crate::{ctx::Context, validators};
+use crate::error::{Diagnostic, Error, Reporter};
+use crate::{body, ctx::Context, validators};
 
 use anyhow::{anyhow, Result};
 use chrono::NaiveDate;
+use std::fmt;
+use std::ops::Range;
 use url::Url;
 
 #[derive(Debug)]
@@ -12,14 +14,44 @@ pub struct Eip {
 }
 
 impl Eip {
-    pub fn from_str(ctx: &Context, s: &str) -> Result<Self, Vec<Error>> {
-        match Preamble::from_str(ctx, s) {
-            Ok((preamble, body)) => Ok(Eip { preamble, body }),
-            Err(e) => Err(e),
+    pub fn from_str(ctx: &Context, s: &str) -> Result<Self, Vec<Diagnostic>> {
+        match Eip::parse(ctx, s)? {
+            (eip, diagnostics) if !diagnostics.iter().any(|d| ctx.fails(d)) => Ok(eip),
+            (_, diagnostics) => Err(diagnostics),
         }
     }
+
+    /// Parses as much of the EIP as possible, returning it along with any
+    /// diagnostics. Fails only if the preamble can't be located.
+    pub fn parse(ctx: &Context, s: &str) -> Result<(Self, Vec<Diagnostic>), Vec<Diagnostic>> {
+        let (preamble, body, mut diagnostics) = Preamble::parse(ctx, s)?;
+
+        let mut errors = Reporter::new(ctx, s);
+        body::validate(ctx, &body, s.len() - body.len(), &mut errors);
+        diagnostics.extend(errors.into_inner());
+
+        Ok((Eip { preamble, body }, diagnostics))
+    }
 }
 
+/// The order preamble fields must appear in, as specified by EIP-1. `updated`
+/// isn't part of it anymore, so it's kept alongside `created`.
+pub const FIELD_ORDER: &[&str] = &[
+    "eip",
+    "title",
+    "description",
+    "author",
+    "discussions-to",
+    "status",
+    "last-call-deadline",
+    "type",
+    "category",
+    "created",
+    "updated",
+    "requires",
+    "withdrawal-reason",
+];
+
 #[derive(Debug, Default)]
 pub struct Preamble {
     pub eip: Option<Result<u64>>,
@@ -35,81 +67,229 @@ pub struct Preamble {
     pub updated: Option<Result<Vec<NaiveDate>>>,
     pub requires: Option<Result<Vec<u64>>>,
     pub withdrawal_reason: Option<Result<String>>,
+    /// Every well-formed field, in the order they appear.
+    pub fields: Vec<Field>,
+}
+
+/// The location of a field in the preamble.
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub key: String,
+    /// Line number of the field, starting from 1.
+    pub line: usize,
+    /// Byte range of the whole line, excluding the newline.
+    pub range: Range<usize>,
+    pub key_range: Range<usize>,
+    pub value_range: Range<usize>,
 }
 
 macro_rules! insert {
+    // a lint is reported without keeping the value from being parsed
+    ($preamble: expr, $validator: expr, $lint: expr, $t: expr) => {{
+        if let Some(e) = $lint($t.0) {
+            $t.1.report(e, $t.2.clone());
+        }
+
+        insert!($preamble, $validator, $t)
+    }};
     ($preamble: expr, $validator: expr, $t: expr) => {{
         let res = $validator($t.0);
 
         match res {
             Ok(v) => $preamble = Some(Ok(v)),
             Err(e) => {
-                $preamble = Some(Err((anyhow!(""))));
-
-                if !$t.2.should_ignore(&e) {
-                    $t.1.push(e);
-                }
+                $preamble = Some(Err(anyhow!("")));
+                $t.1.report(e, $t.2);
             }
         }
     }};
 }
 
 impl Preamble {
-    pub fn from_str(ctx: &Context, s: &str) -> Result<(Self, String), Vec<Error>> {
-        let mut preamble = Preamble::default();
-        let mut errors: Vec<Error> = vec![];
+    /// Returns the first occurrence of the field named `key`.
+    pub fn field(&self, key: &str) -> Option<&Field> {
+        self.fields.iter().find(|f| f.key == key)
+    }
 
-        let (block, rest) = validators::preamble(s).map_err(|e| vec![e])?;
+    /// Reports the first field which is out of order, along with the field it
+    /// should follow. Unknown and repeated fields are skipped.
+    fn validate_order(&self, errors: &mut Reporter) {
+        let position = |f: &Field| FIELD_ORDER.iter().position(|k| *k == f.key);
+
+        let fields: Vec<(&Field, usize)> = self
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(i, f)| !self.fields[..*i].iter().any(|g| g.key == f.key))
+            .filter_map(|(_, f)| Some((f, position(f)?)))
+            .collect();
+
+        let mut last = 0;
+
+        for (field, p) in fields.iter() {
+            if *p < last {
+                let after = fields
+                    .iter()
+                    .filter(|(_, q)| q < p)
+                    .max_by_key(|(_, q)| *q)
+                    .map(|(f, _)| f.key.clone());
+
+                errors.report(
+                    Error::OutOfOrderPreambleField {
+                        field: field.key.clone(),
+                        after,
+                    },
+                    field.key_range.clone(),
+                );
+                return;
+            }
 
-        for (i, line) in block.lines().enumerate() {
-            let split_idx = line.find(":");
-            if split_idx.is_none() {
-                errors.push(Error::MalformedField);
-                continue;
+            last = *p;
+        }
+    }
+
+    pub fn from_str(ctx: &Context, s: &str) -> Result<(Self, String), Vec<Diagnostic>> {
+        match Preamble::parse(ctx, s)? {
+            (preamble, rest, diagnostics) if !diagnostics.iter().any(|d| ctx.fails(d)) => {
+                Ok((preamble, rest))
             }
+            (_, _, diagnostics) => Err(diagnostics),
+        }
+    }
 
-            let (mut key, mut value) = line.split_at(split_idx.unwrap());
+    #[allow(clippy::type_complexity)]
+    pub fn parse(
+        ctx: &Context,
+        s: &str,
+    ) -> Result<(Self, String, Vec<Diagnostic>), Vec<Diagnostic>> {
+        let mut preamble = Preamble::default();
+        let mut errors = Reporter::new(ctx, s);
 
-            // sanitize key
-            if key != key.trim_start() {
-                errors.push(Error::LeadingWhitespace);
+        let (block, rest) = match validators::preamble(s) {
+            Ok(v) => v,
+            Err(e) => {
+                errors.report(e, 0..s.len().min(3));
+                return Err(errors.into_inner());
             }
+        };
+
+        // the block starts immediately after the initial delimiter
+        let mut offset = 4;
 
-            key = key.trim_start();
+        for line in block.split_inclusive('\n') {
+            let start = offset;
+            offset += line.len();
+
+            let line = line.strip_suffix('\n').unwrap_or(line);
+
+            let split_idx = match line.find(':') {
+                Some(idx) => idx,
+                None => {
+                    errors.report(Error::MalformedField, start..start + line.len());
+                    continue;
+                }
+            };
+
+            let (raw_key, raw_value) = line.split_at(split_idx);
+
+            // sanitize key
+            let mut key = raw_key.trim_start();
+            let key_start = start + raw_key.len() - key.len();
+
+            if key_start != start {
+                errors.report(Error::LeadingWhitespace, start..key_start);
+            }
 
             if key != key.trim_end() {
-                errors.push(Error::ExtraWhitespace);
+                errors.report(
+                    Error::ExtraWhitespace,
+                    key_start + key.trim_end().len()..start + split_idx,
+                );
             }
 
             key = key.trim_end();
 
             // sanitize value
-            value = value.strip_prefix(":").unwrap();
-
-            if value.len() - 1 > value.trim_start().len() {
-                errors.push(Error::ExtraWhitespace);
-            } else if &value[1..] != value.trim_start() {
-                errors.push(Error::MissingSpaceAfterColon);
+            let raw_value = &raw_value[1..];
+            let raw_value_start = start + split_idx + 1;
+
+            let mut value = raw_value.trim_start();
+            let value_start = raw_value_start + raw_value.len() - value.len();
+
+            if value_start - raw_value_start > 1 {
+                errors.report(Error::ExtraWhitespace, raw_value_start..value_start);
+            } else if value_start == raw_value_start {
+                errors.report(
+                    Error::MissingSpaceAfterColon,
+                    raw_value_start - 1..raw_value_start,
+                );
             }
 
-            value = value.trim_start();
-
             if value != value.trim_end() {
-                errors.push(Error::TrailingWhitespace);
+                errors.report(
+                    Error::TrailingWhitespace,
+                    value_start + value.trim_end().len()..value_start + value.len(),
+                );
             }
 
             value = value.trim_end();
 
+            let field = Field {
+                key: key.to_string(),
+                line: s[..start].matches('\n').count() + 1,
+                range: start..start + line.len(),
+                key_range: key_start..key_start + key.len(),
+                value_range: value_start..value_start + value.len(),
+            };
+
+            // only the first occurrence is validated, later ones are reported
+            if let Some(first) = preamble.field(key) {
+                errors.report(
+                    Error::DuplicatePreambleField {
+                        field: key.to_string(),
+                        first: first.line,
+                        second: field.line,
+                    },
+                    field.key_range.clone(),
+                );
+                preamble.fields.push(field);
+                continue;
+            }
+
+            preamble.fields.push(field);
+
             // tuple to simplify macro calls
-            let t = (value, &mut errors, &ctx);
+            let t = (value, &mut errors, value_start..value_start + value.len());
 
             match key {
                 "eip" => insert!(preamble.eip, validators::eip, t),
-                "title" => insert!(preamble.title, validators::title, t),
-                "description" => insert!(preamble.description, validators::description, t),
-                "author" => insert!(preamble.author, validators::author, t),
+                "title" => insert!(
+                    preamble.title,
+                    validators::title,
+                    |s| validators::title_length(s, ctx.title_max_length()),
+                    t
+                ),
+                "description" => insert!(
+                    preamble.description,
+                    validators::description,
+                    |s| validators::description_length(s, ctx.description_max_length()),
+                    t
+                ),
+                "author" => insert!(
+                    preamble.author,
+                    validators::author,
+                    validators::csv_spacing,
+                    t
+                ),
                 "discussions-to" => insert!(preamble.discussions_to, validators::discussions_to, t),
-                "status" => insert!(preamble.status, validators::status, t),
+                "status" => insert!(
+                    preamble.status,
+                    |s| validators::status(s).and_then(|v| match ctx.allows_status(&v) {
+                        true => Ok(v),
+                        false => Err(Error::UnknownStatus),
+                    }),
+                    t
+                ),
                 "last-call-deadline" => {
                     insert!(
                         preamble.last_call_deadline,
@@ -118,58 +298,181 @@ impl Preamble {
                     )
                 }
                 "type" => insert!(preamble.ty, validators::ty, t),
-                "category" => insert!(preamble.category, validators::category, t),
+                "category" => insert!(
+                    preamble.category,
+                    |s| validators::category(s).and_then(|v| match ctx.allows_category(&v) {
+                        true => Ok(v),
+                        false => Err(Error::UnknownCategory),
+                    }),
+                    t
+                ),
                 "created" => insert!(preamble.created, validators::created, t),
-                "updated" => insert!(preamble.updated, validators::updated, t),
-                "requires" => insert!(preamble.requires, validators::requires, t),
+                "updated" => insert!(
+                    preamble.updated,
+                    validators::updated,
+                    validators::csv_spacing,
+                    t
+                ),
+                "requires" => insert!(
+                    preamble.requires,
+                    validators::requires,
+                    |s| validators::csv_spacing(s).or_else(|| validators::requires_order(s)),
+                    t
+                ),
                 "withdrawal-reason" => {
                     insert!(preamble.withdrawal_reason, validators::withdrawal_reason, t)
                 }
-                _ => errors.push(Error::UnknownPreambleField),
+                _ => errors.report(
+                    Error::UnknownPreambleField,
+                    key_start..key_start + key.len(),
+                ),
             }
         }
 
-        if preamble.eip.is_none() && !ctx.should_ignore(&Error::MissingEipField) {
-            errors.push(Error::MissingEipField);
+        // missing fields are reported against the initial delimiter
+        let delimiter = 0..3;
+
+        if preamble.eip.is_none() {
+            errors.report(Error::MissingEipField, delimiter.clone());
         }
 
-        if preamble.title.is_none() && !ctx.should_ignore(&Error::MissingTitleField) {
-            errors.push(Error::MissingTitleField);
+        if preamble.title.is_none() {
+            errors.report(Error::MissingTitleField, delimiter.clone());
         }
 
-        if preamble.author.is_none() && !ctx.should_ignore(&Error::MissingAuthorField) {
-            errors.push(Error::MissingAuthorField);
+        if preamble.author.is_none() {
+            errors.report(Error::MissingAuthorField, delimiter.clone());
         }
 
-        if preamble.discussions_to.is_none()
-            && !ctx.should_ignore(&Error::MissingDiscussionsToField)
-        {
-            errors.push(Error::MissingDiscussionsToField);
+        if preamble.discussions_to.is_none() {
+            errors.report(Error::MissingDiscussionsToField, delimiter.clone());
         }
 
-        if preamble.status.is_none() && !ctx.should_ignore(&Error::MissingStatusField) {
-            errors.push(Error::MissingStatusField);
+        if preamble.status.is_none() {
+            errors.report(Error::MissingStatusField, delimiter.clone());
         }
 
         if let Some(Ok(ty)) = preamble.ty {
-            if ty == Type::Standards
-                && preamble.category.is_none()
-                && !ctx.should_ignore(&Error::MissingCategoryField)
-            {
-                errors.push(Error::MissingCategoryField);
+            if ty == Type::Standards && preamble.category.is_none() {
+                errors.report(Error::MissingCategoryField, delimiter.clone());
             }
-        } else if !ctx.should_ignore(&Error::MissingTypeField) && preamble.ty.is_none() {
-            errors.push(Error::MissingTypeField);
+        } else if preamble.ty.is_none() {
+            errors.report(Error::MissingTypeField, delimiter.clone());
         }
 
-        match errors.is_empty() {
-            true => Ok((preamble, rest.to_string())),
-            false => Err(errors),
+        preamble.validate_order(&mut errors);
+
+        // some fields only belong to a particular status
+        if let Some(Ok(status)) = preamble.status {
+            match (status, &preamble.last_call_deadline) {
+                (Status::LastCall, None) => {
+                    errors.report(Error::MissingLastCallDeadlineField, delimiter.clone())
+                }
+                (Status::LastCall, _) | (_, None) => (),
+                (_, Some(_)) => errors.report(
+                    Error::UnexpectedLastCallDeadline,
+                    preamble
+                        .field("last-call-deadline")
+                        .unwrap()
+                        .value_range
+                        .clone(),
+                ),
+            }
+
+            match (status, &preamble.withdrawal_reason) {
+                (Status::Withdrawn, None) => {
+                    errors.report(Error::MissingWithdrawalReasonField, delimiter)
+                }
+                (Status::Withdrawn, _) | (_, None) => (),
+                (_, Some(_)) => errors.report(
+                    Error::UnexpectedWithdrawalReason,
+                    preamble
+                        .field("withdrawal-reason")
+                        .unwrap()
+                        .value_range
+                        .clone(),
+                ),
+            }
+        }
+
+        // dates must be consistent with each other, and only the last call
+        // deadline may be in the future
+        let today = ctx.today();
+
+        if let Some(Ok(created)) = preamble.created {
+            if today < created {
+                errors.report(
+                    Error::FutureDate("created".to_string()),
+                    preamble.field("created").unwrap().value_range.clone(),
+                );
+            }
+
+            if let Some(Ok(deadline)) = preamble.last_call_deadline {
+                if deadline <= created {
+                    errors.report(
+                        Error::LastCallDeadlineBeforeCreated,
+                        preamble
+                            .field("last-call-deadline")
+                            .unwrap()
+                            .value_range
+                            .clone(),
+                    );
+                }
+            }
+
+            if let Some(Ok(updated)) = &preamble.updated {
+                if updated.iter().any(|d| *d <= created) {
+                    errors.report(
+                        Error::UpdatedBeforeCreated,
+                        preamble.field("updated").unwrap().value_range.clone(),
+                    );
+                }
+            }
+        }
+
+        if let Some(Ok(updated)) = &preamble.updated {
+            if updated.windows(2).any(|w| w[1] <= w[0]) {
+                errors.report(
+                    Error::UpdatedOutOfOrder,
+                    preamble.field("updated").unwrap().value_range.clone(),
+                );
+            }
+
+            if updated.iter().any(|d| today < *d) {
+                errors.report(
+                    Error::FutureDate("updated".to_string()),
+                    preamble.field("updated").unwrap().value_range.clone(),
+                );
+            }
+        }
+
+        if let (Some(Ok(eip)), Some(Ok(r))) = (&preamble.eip, &preamble.requires) {
+            if r.contains(eip) {
+                errors.report(
+                    Error::RequiresSelf,
+                    preamble.field("requires").unwrap().value_range.clone(),
+                );
+            }
         }
+
+        if let Some(Ok(url)) = &preamble.discussions_to {
+            for e in validators::discussions_to_rules(url, ctx.discussions_to_host()) {
+                errors.report(
+                    e,
+                    preamble
+                        .field("discussions-to")
+                        .unwrap()
+                        .value_range
+                        .clone(),
+                );
+            }
+        }
+
+        Ok((preamble, rest.to_string(), errors.into_inner()))
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Status {
     Draft,
     Review,
@@ -193,6 +496,34 @@ impl Status {
             _ => Err(Error::UnknownStatus),
         }
     }
+
+    /// Returns whether an EIP with this status may require one with status
+    /// `other`.
+    pub fn may_require(&self, other: &Status) -> bool {
+        use Status::*;
+
+        match self {
+            Final => !matches!(other, Draft | Review | Stagnant | Withdrawn),
+            LastCall => matches!(other, LastCall | Final | Living),
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Draft => "Draft",
+            Self::Review => "Review",
+            Self::LastCall => "Last Call",
+            Self::Final => "Final",
+            Self::Stagnant => "Stagnant",
+            Self::Withdrawn => "Withdrawn",
+            Self::Living => "Living",
+        };
+
+        write!(f, "{}", s)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -213,7 +544,19 @@ impl Type {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Standards => "Standards Track",
+            Self::Informational => "Informational",
+            Self::Meta => "Meta",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Category {
     Core,
     Networking,
@@ -232,3 +575,16 @@ impl Category {
         }
     }
 }
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Core => "Core",
+            Self::Networking => "Networking",
+            Self::Interface => "Interface",
+            Self::Erc => "ERC",
+        };
+
+        write!(f, "{}", s)
+    }
+}
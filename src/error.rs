@@ -1,7 +1,102 @@
+use crate::ctx::Context;
+use crate::eip::Status;
+use crate::validators::{DESCRIPTION_MAX_LEN, TITLE_MAX_LEN};
+
 use anyhow::anyhow;
+use std::fmt;
+use std::ops::Range;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Location of a diagnostic within an EIP. Lines and columns are 1-based,
+/// while `start` and `end` are byte offsets into the source as given, CRLF
+/// line endings included.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(src: &str, range: Range<usize>) -> Self {
+        let before = &src[..range.start];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        Span {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            start: range.start,
+            end: range.end,
+        }
+    }
+
+    /// Moves the offsets from the normalized source back to the original,
+    /// where the newlines at `crlf` were preceded by `\r`. Lines and columns
+    /// don't change.
+    pub(crate) fn denormalize(&mut self, crlf: &[usize]) {
+        self.start += crlf.partition_point(|p| *p < self.start);
+        self.end += crlf.partition_point(|p| *p < self.end);
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// An `Error` along with where it occurred.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub error: Error,
+    pub span: Span,
+    pub severity: Severity,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            // errors are the common case, so they aren't labelled
+            Severity::Error => write!(f, "{}: {}", self.span, self.error),
+            s => write!(f, "{}: {}: {}", self.span, s, self.error),
+        }
+    }
+}
+
+/// Collects the diagnostics reported against a single source, dropping any
+/// which the context ignores.
+pub(crate) struct Reporter<'a> {
+    ctx: &'a Context,
+    src: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Reporter<'a> {
+    pub fn new(ctx: &'a Context, src: &'a str) -> Self {
+        Reporter {
+            ctx,
+            src,
+            diagnostics: vec![],
+        }
+    }
+
+    pub fn report(&mut self, error: Error, range: Range<usize>) {
+        if !self.ctx.should_ignore(&error) {
+            self.diagnostics.push(Diagnostic {
+                severity: self.ctx.severity(&error),
+                span: Span::new(self.src, range),
+                error,
+            });
+        }
+    }
+
+    pub fn into_inner(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Error {
     // generic errors
@@ -11,6 +106,15 @@ pub enum Error {
     ExtraWhitespace,
     TrailingWhitespace,
     UnknownPreambleField,
+    OutOfOrderPreambleField {
+        field: String,
+        after: Option<String>,
+    },
+    DuplicatePreambleField {
+        field: String,
+        first: usize,
+        second: usize,
+    },
 
     // missing field
     MissingEipField,
@@ -20,14 +124,23 @@ pub enum Error {
     MissingStatusField,
     MissingCategoryField,
     MissingTypeField,
+    MissingLastCallDeadlineField,
+    MissingWithdrawalReasonField,
+
+    // field not allowed for the status
+    UnexpectedLastCallDeadline,
+    UnexpectedWithdrawalReason,
 
     // validator level errors
     StartDelimiterMissing,
     EndDelimiterMissing,
     MalformedEipNumber,
-    TitleExceedsMaxLength,
-    DescriptionExceedsMaxLength,
+    TitleExceedsMaxLength(usize),
+    DescriptionExceedsMaxLength(usize),
     MalformedDiscussionsTo,
+    DiscussionsToPullRequest,
+    DiscussionsToInsecure,
+    DiscussionsToHost(String),
     UnknownStatus,
     UnknownType,
     UnknownCategory,
@@ -44,18 +157,255 @@ pub enum Error {
     TrailingInfoAfterHandle,
     MalformedEmail,
     MalformedHandle,
+    UpdatedBeforeCreated,
+    UpdatedOutOfOrder,
+    LastCallDeadlineBeforeCreated,
+    FutureDate(String),
+    RequiresSelf,
+    RequiresUnknownEip(u64),
+    RequiresCycle(Vec<u64>),
+    RequiresStatus {
+        eip: u64,
+        status: Status,
+        requires: u64,
+        requires_status: Status,
+    },
+
+    // body level errors
+    MissingSection(String),
+    UnknownSection(String),
+    DuplicateSection(String),
+    OutOfOrderSection(String),
+    AbstractTooLong {
+        words: usize,
+        max: usize,
+    },
+    MissingCopyrightWaiver,
+    ModifiedCopyrightWaiver,
+    ContentAfterCopyrightWaiver,
+    UnmatchedBracket(char),
+    AbsoluteEipLink(String),
+    MissingLinkedEip(String),
+
+    // file level errors
+    MalformedFileName(String),
+    FileNameMismatch(String),
+}
+
+/// How seriously a diagnostic should be taken.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Info => "info",
+        };
+
+        f.pad(s)
+    }
+}
+
+/// Defines `Error::all`, which returns each of the listed variants in order.
+/// Every variant must be listed, or the exhaustive match below won't compile.
+macro_rules! all {
+    ($($variant: ident $(($($arg: expr),* $(,)?))? $({$($field: ident: $value: expr),* $(,)?})?),* $(,)?) => {
+        /// Returns every variant, in declaration order.
+        pub fn all() -> Vec<Self> {
+            vec![$(Self::$variant $(($($arg),*))? $({$($field: $value),*})?),*]
+        }
+
+        #[allow(dead_code)]
+        fn listed(&self) {
+            match self {
+                $(Self::$variant { .. })|* => (),
+            }
+        }
+    };
 }
 
 impl Error {
+    /// Looks up an error by its code. The original underscore-separated
+    /// validator names are still accepted.
     pub fn from_str(s: &str) -> anyhow::Result<Self> {
         match s {
-            "title_max_length" => Ok(Self::TitleExceedsMaxLength),
-            "description_max_length" => Ok(Self::TitleExceedsMaxLength),
+            "title_max_length" => Ok(Self::TitleExceedsMaxLength(TITLE_MAX_LEN)),
+            "description_max_length" => Ok(Self::DescriptionExceedsMaxLength(DESCRIPTION_MAX_LEN)),
             "missing_discussions_to" => Ok(Self::MissingDiscussionsToField),
-            _ => Err(anyhow!("unknown validator")),
+            _ => Self::all()
+                .into_iter()
+                .find(|e| e.code() == s)
+                .ok_or_else(|| anyhow!("unknown validator: {}", s)),
         }
     }
 
+    /// A stable identifier for the error, used to refer to it from the
+    /// command line and in machine-readable output.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::MalformedField => "preamble-malformed-field",
+            Self::MissingSpaceAfterColon => "preamble-missing-space-after-colon",
+            Self::LeadingWhitespace => "preamble-leading-whitespace",
+            Self::ExtraWhitespace => "preamble-extra-whitespace",
+            Self::TrailingWhitespace => "preamble-trailing-whitespace",
+            Self::UnknownPreambleField => "preamble-unknown-field",
+            Self::DuplicatePreambleField { .. } => "preamble-duplicate-field",
+            Self::OutOfOrderPreambleField { .. } => "preamble-out-of-order-field",
+            Self::MissingEipField => "preamble-missing-eip",
+            Self::MissingTitleField => "preamble-missing-title",
+            Self::MissingAuthorField => "preamble-missing-author",
+            Self::MissingDiscussionsToField => "preamble-missing-discussions-to",
+            Self::MissingStatusField => "preamble-missing-status",
+            Self::MissingCategoryField => "preamble-missing-category",
+            Self::MissingTypeField => "preamble-missing-type",
+            Self::MissingLastCallDeadlineField => "preamble-missing-last-call-deadline",
+            Self::MissingWithdrawalReasonField => "preamble-missing-withdrawal-reason",
+            Self::UnexpectedLastCallDeadline => "preamble-unexpected-last-call-deadline",
+            Self::UnexpectedWithdrawalReason => "preamble-unexpected-withdrawal-reason",
+            Self::StartDelimiterMissing => "preamble-start-delimiter-missing",
+            Self::EndDelimiterMissing => "preamble-end-delimiter-missing",
+            Self::MalformedEipNumber => "preamble-malformed-eip-number",
+            Self::TitleExceedsMaxLength(_) => "preamble-title-max-length",
+            Self::DescriptionExceedsMaxLength(_) => "preamble-description-max-length",
+            Self::MalformedDiscussionsTo => "preamble-malformed-discussions-to",
+            Self::DiscussionsToPullRequest => "preamble-discussions-to-pull-request",
+            Self::DiscussionsToInsecure => "preamble-discussions-to-https",
+            Self::DiscussionsToHost(_) => "preamble-discussions-to-host",
+            Self::UnknownStatus => "preamble-unknown-status",
+            Self::UnknownType => "preamble-unknown-type",
+            Self::UnknownCategory => "preamble-unknown-category",
+            Self::MalformedLastCallDeadline => "preamble-malformed-last-call-deadline",
+            Self::MalformedCreated => "preamble-malformed-created",
+            Self::MalformedUpdated => "preamble-malformed-updated",
+            Self::MissingSpaceAfterComma => "preamble-missing-space-after-comma",
+            Self::ExtraWhitespaceBeforeComma => "preamble-whitespace-before-comma",
+            Self::OutOfOrderEips => "preamble-out-of-order-eips",
+            Self::UnmatchedEmailDelimiter => "preamble-author-unmatched-email-delimiter",
+            Self::UnmatchedHandleDelimiter => "preamble-author-unmatched-handle-delimiter",
+            Self::AuthorHasEmailAndHandle => "preamble-author-email-and-handle",
+            Self::TrailingInfoAfterEmail => "preamble-author-trailing-info-after-email",
+            Self::TrailingInfoAfterHandle => "preamble-author-trailing-info-after-handle",
+            Self::MalformedEmail => "preamble-author-malformed-email",
+            Self::MalformedHandle => "preamble-author-malformed-handle",
+            Self::UpdatedBeforeCreated => "preamble-updated-before-created",
+            Self::UpdatedOutOfOrder => "preamble-updated-out-of-order",
+            Self::LastCallDeadlineBeforeCreated => "preamble-last-call-deadline-before-created",
+            Self::FutureDate(_) => "preamble-future-date",
+            Self::RequiresSelf => "preamble-requires-self",
+            Self::RequiresUnknownEip(_) => "preamble-requires-unknown-eip",
+            Self::RequiresCycle(_) => "preamble-requires-cycle",
+            Self::RequiresStatus { .. } => "preamble-requires-status",
+            Self::MissingSection(_) => "body-missing-section",
+            Self::UnknownSection(_) => "body-unknown-section",
+            Self::DuplicateSection(_) => "body-duplicate-section",
+            Self::OutOfOrderSection(_) => "body-out-of-order-section",
+            Self::AbstractTooLong { .. } => "body-abstract-max-words",
+            Self::MissingCopyrightWaiver => "body-missing-copyright-waiver",
+            Self::ModifiedCopyrightWaiver => "body-modified-copyright-waiver",
+            Self::ContentAfterCopyrightWaiver => "body-content-after-copyright-waiver",
+            Self::UnmatchedBracket(_) => "body-unmatched-bracket",
+            Self::AbsoluteEipLink(_) => "body-absolute-eip-link",
+            Self::MissingLinkedEip(_) => "body-missing-linked-eip",
+            Self::MalformedFileName(_) => "file-name-malformed",
+            Self::FileNameMismatch(_) => "file-name-mismatch",
+        }
+    }
+
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            Self::UnexpectedLastCallDeadline | Self::UnexpectedWithdrawalReason => {
+                Severity::Warning
+            }
+            _ => Severity::Error,
+        }
+    }
+
+    all! {
+        MalformedField,
+        MissingSpaceAfterColon,
+        LeadingWhitespace,
+        ExtraWhitespace,
+        TrailingWhitespace,
+        UnknownPreambleField,
+        OutOfOrderPreambleField {
+            field: String::new(),
+            after: None,
+        },
+        DuplicatePreambleField {
+            field: String::new(),
+            first: 0,
+            second: 0,
+        },
+        MissingEipField,
+        MissingTitleField,
+        MissingAuthorField,
+        MissingDiscussionsToField,
+        MissingStatusField,
+        MissingCategoryField,
+        MissingTypeField,
+        MissingLastCallDeadlineField,
+        MissingWithdrawalReasonField,
+        UnexpectedLastCallDeadline,
+        UnexpectedWithdrawalReason,
+        StartDelimiterMissing,
+        EndDelimiterMissing,
+        MalformedEipNumber,
+        TitleExceedsMaxLength(TITLE_MAX_LEN),
+        DescriptionExceedsMaxLength(DESCRIPTION_MAX_LEN),
+        MalformedDiscussionsTo,
+        DiscussionsToPullRequest,
+        DiscussionsToInsecure,
+        DiscussionsToHost(String::new()),
+        UnknownStatus,
+        UnknownType,
+        UnknownCategory,
+        MalformedLastCallDeadline,
+        MalformedCreated,
+        MalformedUpdated,
+        MissingSpaceAfterComma,
+        ExtraWhitespaceBeforeComma,
+        OutOfOrderEips,
+        UnmatchedEmailDelimiter,
+        UnmatchedHandleDelimiter,
+        AuthorHasEmailAndHandle,
+        TrailingInfoAfterEmail,
+        TrailingInfoAfterHandle,
+        MalformedEmail,
+        MalformedHandle,
+        UpdatedBeforeCreated,
+        UpdatedOutOfOrder,
+        LastCallDeadlineBeforeCreated,
+        FutureDate(String::new()),
+        RequiresSelf,
+        RequiresUnknownEip(0),
+        RequiresCycle(vec![]),
+        RequiresStatus {
+            eip: 0,
+            status: Status::Final,
+            requires: 0,
+            requires_status: Status::Draft,
+        },
+        MissingSection(String::new()),
+        UnknownSection(String::new()),
+        DuplicateSection(String::new()),
+        OutOfOrderSection(String::new()),
+        AbstractTooLong { words: 0, max: 0 },
+        MissingCopyrightWaiver,
+        ModifiedCopyrightWaiver,
+        ContentAfterCopyrightWaiver,
+        UnmatchedBracket('('),
+        AbsoluteEipLink(String::new()),
+        MissingLinkedEip(String::new()),
+        MalformedFileName(String::new()),
+        FileNameMismatch(String::new()),
+    }
+
     pub fn human_readable(&self) -> &'static str {
         match &self {
             // preamble level errors
@@ -65,6 +415,8 @@ impl Error {
             Self::TrailingWhitespace => "trailing whitespace",
             Self::LeadingWhitespace => "leading whitespace",
             Self::UnknownPreambleField => "unknown preamble field",
+            Self::DuplicatePreambleField { .. } => "preamble fields can't be repeated",
+            Self::OutOfOrderPreambleField { .. } => "preamble fields must be in the order of EIP-1",
 
             // missing required fields
             Self::MissingEipField => "missing EIP field in preamble",
@@ -74,14 +426,25 @@ impl Error {
             Self::MissingStatusField => "missing status field in preamble",
             Self::MissingCategoryField => "missing category field in preamble",
             Self::MissingTypeField => "missing type field in preamble",
+            Self::MissingLastCallDeadlineField => {
+                "missing last-call-deadline field in preamble, required in Last Call"
+            }
+            Self::MissingWithdrawalReasonField => {
+                "missing withdrawal-reason field in preamble, required when Withdrawn"
+            }
+            Self::UnexpectedLastCallDeadline => "last-call-deadline is only used in Last Call",
+            Self::UnexpectedWithdrawalReason => "withdrawal-reason is only used when Withdrawn",
 
             // validator level errors
             Self::StartDelimiterMissing => "missing initial '---' in preamble",
             Self::EndDelimiterMissing => "missing trailing '---' in preamble",
             Self::MalformedEipNumber => "EIP should be an unsigned integer",
-            Self::TitleExceedsMaxLength => "title exceeds max length of 44 characters",
-            Self::DescriptionExceedsMaxLength => "description exceeds max length of 140 characters",
+            Self::TitleExceedsMaxLength(_) => "title exceeds max length",
+            Self::DescriptionExceedsMaxLength(_) => "description exceeds max length",
             Self::MalformedDiscussionsTo => "discussions-to must be a URL",
+            Self::DiscussionsToPullRequest => "discussions-to can't point to a pull request",
+            Self::DiscussionsToInsecure => "discussions-to must use https",
+            Self::DiscussionsToHost(_) => "discussions-to must be on the configured host",
             Self::UnknownStatus => "unknown status",
             Self::UnknownType => "unknown type",
             Self::UnknownCategory => "unknown category",
@@ -102,6 +465,109 @@ impl Error {
             Self::TrailingInfoAfterHandle => "trailing information after handle",
             Self::MalformedEmail => "malformed email",
             Self::MalformedHandle => "malformed handle",
+            Self::UpdatedBeforeCreated => "updated dates must be after the created date",
+            Self::UpdatedOutOfOrder => "updated dates must be in ascending order",
+            Self::LastCallDeadlineBeforeCreated => {
+                "last-call-deadline must be after the created date"
+            }
+            Self::FutureDate(_) => "date is in the future",
+            Self::RequiresSelf => "EIP can't require itself",
+            Self::RequiresUnknownEip(_) => "required EIP doesn't exist",
+            Self::RequiresCycle(_) => "EIPs can't require each other in a cycle",
+            Self::RequiresStatus { .. } => "required EIP's status is incompatible",
+
+            // body level errors
+            Self::MissingSection(_) => "missing required section",
+            Self::UnknownSection(_) => "unknown section",
+            Self::DuplicateSection(_) => "duplicate section",
+            Self::OutOfOrderSection(_) => "section is out of order",
+            Self::AbstractTooLong { .. } => "abstract exceeds max word count",
+            Self::MissingCopyrightWaiver => "missing `Copyright` section",
+            Self::ModifiedCopyrightWaiver => {
+                "copyright waiver must be `Copyright and related rights waived via CC0.`"
+            }
+            Self::ContentAfterCopyrightWaiver => "unexpected content after copyright waiver",
+            Self::UnmatchedBracket(_) => "unmatched bracket outside of code",
+            Self::AbsoluteEipLink(_) => "links to other EIPs must be relative",
+            Self::MissingLinkedEip(_) => "linked EIP doesn't exist",
+
+            // file level errors
+            Self::MalformedFileName(_) => "malformed file name",
+            Self::FileNameMismatch(_) => "file name doesn't match the EIP number",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfOrderPreambleField { field, after } => match after {
+                Some(after) => write!(f, "`{}` field should come after `{}`", field, after),
+                None => write!(f, "`{}` field should come first", field),
+            },
+            Self::DuplicatePreambleField {
+                field,
+                first,
+                second,
+            } => write!(
+                f,
+                "duplicate `{}` field on line {}, first given on line {}",
+                field, second, first
+            ),
+            Self::TitleExceedsMaxLength(max) => {
+                write!(f, "title exceeds max length of {} characters", max)
+            }
+            Self::DescriptionExceedsMaxLength(max) => {
+                write!(f, "description exceeds max length of {} characters", max)
+            }
+            Self::FutureDate(field) => write!(f, "{} date is in the future", field),
+            Self::RequiresUnknownEip(n) => write!(f, "required EIP {} doesn't exist", n),
+            Self::RequiresCycle(path) => {
+                let path: Vec<String> = path.iter().map(|n| n.to_string()).collect();
+                write!(f, "circular requirement: {}", path.join(" -> "))
+            }
+            Self::RequiresStatus {
+                eip,
+                status,
+                requires,
+                requires_status,
+            } => write!(
+                f,
+                "EIP {} ({}) can't require EIP {} ({})",
+                eip, status, requires, requires_status
+            ),
+            Self::MissingSection(s) => write!(f, "missing required section `{}`", s),
+            Self::UnknownSection(s) => write!(f, "unknown section `{}`", s),
+            Self::DuplicateSection(s) => write!(f, "duplicate section `{}`", s),
+            Self::OutOfOrderSection(s) => write!(f, "section `{}` is out of order", s),
+            Self::UnmatchedBracket(c) => write!(f, "unmatched `{}`", c),
+            Self::DiscussionsToHost(host) => write!(f, "discussions-to must be on `{}`", host),
+            Self::AbsoluteEipLink(relative) => {
+                write!(f, "link to EIP should be relative: `{}`", relative)
+            }
+            Self::MissingLinkedEip(name) => write!(f, "linked file `{}` doesn't exist", name),
+            Self::MalformedFileName(form) => write!(f, "file name must be of the form `{}`", form),
+            Self::FileNameMismatch(name) => {
+                write!(f, "file name should be `{}` to match the EIP number", name)
+            }
+            Self::AbstractTooLong { words, max } => write!(
+                f,
+                "abstract is {} words, exceeding the max of {} words",
+                words, max
+            ),
+            _ => write!(f, "{}", self.human_readable()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_round_trip() {
+        for e in Error::all() {
+            assert_eq!(Error::from_str(e.code()).unwrap(), e);
         }
     }
 }
@@ -0,0 +1,121 @@
+use crate::ctx::Context;
+use crate::eip::{Preamble, FIELD_ORDER};
+use crate::validators;
+
+use std::collections::HashSet;
+
+/// Fields holding comma-separated values.
+const CSV_FIELDS: &[&str] = &["author", "updated", "requires"];
+
+/// Fixes every error which has only one possible fix: whitespace around
+/// preamble keys, values and commas, unordered `requires` numbers and the
+/// order of the fields. The line ending of each line is preserved.
+pub fn fix(s: &str) -> String {
+    let src = crate::normalize(s);
+    let crlf = crate::crlf_offsets(s);
+
+    // fixes never add or remove lines, so endings are kept by line number
+    let mut offset = 0;
+    let crlf_lines: HashSet<usize> = src
+        .split_inclusive('\n')
+        .enumerate()
+        .filter_map(|(i, line)| {
+            offset += line.len();
+            crlf.binary_search(&(offset - 1)).ok().map(|_| i)
+        })
+        .collect();
+
+    let fixed = reorder_preamble(&fix_preamble_whitespace(&src));
+
+    fixed
+        .split_inclusive('\n')
+        .enumerate()
+        .map(|(i, line)| match crlf_lines.contains(&i) {
+            true => line.replace('\n', "\r\n"),
+            false => line.to_string(),
+        })
+        .collect()
+}
+
+/// Rewrites each preamble field as `key: value`, with comma-separated values
+/// separated by `, ` and required EIPs in ascending order.
+fn fix_preamble_whitespace(s: &str) -> String {
+    let (block, _) = match validators::preamble(s) {
+        Ok(v) => v,
+        Err(_) => return s.to_string(),
+    };
+
+    let mut out = s[..4].to_string();
+
+    for line in block.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => {
+                out.push_str(line);
+                out.push('\n');
+                continue;
+            }
+        };
+
+        let value = match CSV_FIELDS.contains(&key) {
+            true => fix_csv(value, key == "requires"),
+            false => value.to_string(),
+        };
+
+        match value.is_empty() {
+            true => out.push_str(&format!("{}:\n", key)),
+            false => out.push_str(&format!("{}: {}\n", key, value)),
+        }
+    }
+
+    out.push_str(&s[4 + block.len()..]);
+    out
+}
+
+/// Separates comma-separated values by `, `, putting them in ascending order
+/// if `sort` is set and they're all numbers.
+fn fix_csv(value: &str, sort: bool) -> String {
+    let mut values: Vec<&str> = value.split(',').map(str::trim).collect();
+
+    if sort && values.iter().all(|v| v.parse::<u64>().is_ok()) {
+        values.sort_by_key(|v| v.parse::<u64>().unwrap());
+    }
+
+    values.join(", ")
+}
+
+/// Sorts the preamble fields into the order of EIP-1, leaving their values
+/// untouched. Unknown fields are moved to the end. The EIP is returned as is
+/// if its preamble can't be located or has lines which aren't fields.
+pub fn reorder_preamble(s: &str) -> String {
+    let (block, _) = match validators::preamble(s) {
+        Ok(v) => v,
+        Err(_) => return s.to_string(),
+    };
+
+    let mut fields = match Preamble::parse(&Context::default(), s) {
+        Ok((preamble, _, _)) => preamble.fields,
+        Err(_) => return s.to_string(),
+    };
+
+    if fields.len() != block.lines().count() {
+        return s.to_string();
+    }
+
+    // the sort is stable, so repeated fields stay after the first
+    fields.sort_by_key(|f| {
+        FIELD_ORDER
+            .iter()
+            .position(|k| *k == f.key)
+            .unwrap_or(FIELD_ORDER.len())
+    });
+
+    let mut out = s[..4].to_string();
+    for f in fields.iter() {
+        out.push_str(&s[f.range.clone()]);
+        out.push('\n');
+    }
+    out.push_str(&s[4 + block.len()..]);
+
+    out
+}
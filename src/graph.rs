@@ -0,0 +1,210 @@
+use crate::eip::Status;
+use crate::runner::Report;
+
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// An EIP in the requires graph.
+#[derive(Debug)]
+pub struct Node {
+    pub title: Option<String>,
+    pub status: Option<Status>,
+    pub requires: Vec<u64>,
+}
+
+/// The dependency graph formed by the `requires` field of a set of EIPs.
+#[derive(Debug, Default)]
+pub struct Graph {
+    nodes: BTreeMap<u64, Node>,
+}
+
+impl Graph {
+    /// Builds the graph from every report whose EIP number could be parsed.
+    pub fn new(reports: &[Report]) -> Self {
+        fn ok<T: Clone>(v: &Option<anyhow::Result<T>>) -> Option<T> {
+            v.as_ref().and_then(|v| v.as_ref().ok()).cloned()
+        }
+
+        let nodes = reports
+            .iter()
+            .filter_map(|r| {
+                let preamble = &r.eip.as_ref()?.preamble;
+                let node = Node {
+                    title: ok(&preamble.title),
+                    status: ok(&preamble.status),
+                    requires: ok(&preamble.requires).unwrap_or_default(),
+                };
+                Some((ok(&preamble.eip)?, node))
+            })
+            .collect();
+
+        Self { nodes }
+    }
+
+    pub fn nodes(&self) -> &BTreeMap<u64, Node> {
+        &self.nodes
+    }
+
+    /// Returns the shortest cycle through each EIP that's in one, as the path
+    /// of EIP numbers it takes, starting and ending at that EIP. An EIP
+    /// requiring itself isn't considered a cycle.
+    pub fn cycles(&self) -> BTreeMap<u64, Vec<u64>> {
+        let mut cycles = BTreeMap::new();
+
+        // a cycle never leaves its strongly connected component, so only
+        // those with several EIPs need to be searched
+        for component in self.components().iter().filter(|c| c.len() > 1) {
+            for &n in component.iter() {
+                if let Some(cycle) = self.shortest_cycle(component, n) {
+                    cycles.insert(n, cycle);
+                }
+            }
+        }
+
+        cycles
+    }
+
+    /// Searches `component` breadth first for the shortest way from `n` back
+    /// to itself.
+    fn shortest_cycle(&self, component: &BTreeSet<u64>, n: u64) -> Option<Vec<u64>> {
+        // the EIP each visited EIP was first reached from
+        let mut parent: BTreeMap<u64, u64> = BTreeMap::new();
+        let mut queue = VecDeque::from(vec![n]);
+
+        while let Some(m) = queue.pop_front() {
+            for &next in self.requires(m) {
+                if next == n && m != n {
+                    let mut path = vec![n, m];
+                    while let Some(p) = parent.get(path.last().unwrap()) {
+                        path.push(*p);
+                    }
+                    path.reverse();
+                    return Some(path);
+                } else if next != n && component.contains(&next) && !parent.contains_key(&next) {
+                    parent.insert(next, m);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn requires(&self, n: u64) -> &[u64] {
+        self.nodes
+            .get(&n)
+            .map(|node| node.requires.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Tarjan's algorithm for the strongly connected components of the graph.
+    fn components(&self) -> Vec<BTreeSet<u64>> {
+        #[derive(Default)]
+        struct State {
+            next: usize,
+            index: BTreeMap<u64, usize>,
+            low: BTreeMap<u64, usize>,
+            stack: Vec<u64>,
+            components: Vec<BTreeSet<u64>>,
+        }
+
+        fn visit(graph: &Graph, n: u64, s: &mut State) {
+            s.index.insert(n, s.next);
+            s.low.insert(n, s.next);
+            s.next += 1;
+            s.stack.push(n);
+
+            for &m in graph.requires(n) {
+                if !s.index.contains_key(&m) {
+                    visit(graph, m, s);
+                    let low = s.low[&n].min(s.low[&m]);
+                    s.low.insert(n, low);
+                } else if s.stack.contains(&m) {
+                    let low = s.low[&n].min(s.index[&m]);
+                    s.low.insert(n, low);
+                }
+            }
+
+            if s.low[&n] == s.index[&n] {
+                let mut component = BTreeSet::new();
+                while let Some(m) = s.stack.pop() {
+                    component.insert(m);
+                    if m == n {
+                        break;
+                    }
+                }
+                s.components.push(component);
+            }
+        }
+
+        let mut state = State::default();
+        for &n in self.nodes.keys() {
+            if !state.index.contains_key(&n) {
+                visit(self, n, &mut state);
+            }
+        }
+
+        state.components
+    }
+
+    /// Renders the graph in Graphviz DOT, with an edge from each EIP to those
+    /// it requires.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph requires {\n");
+
+        for (n, node) in self.nodes.iter() {
+            let label = match &node.title {
+                Some(title) => format!("EIP-{}: {}", n, title),
+                None => format!("EIP-{}", n),
+            };
+            out.push_str(&format!(
+                "    {} [label=\"{}\"];\n",
+                n,
+                label.replace('\\', "\\\\").replace('"', "\\\"")
+            ));
+        }
+
+        for (n, node) in self.nodes.iter() {
+            for r in node.requires.iter() {
+                out.push_str(&format!("    {} -> {};\n", n, r));
+            }
+        }
+
+        out.push('}');
+        out
+    }
+
+    pub fn to_json(&self) -> Value {
+        let nodes: Vec<Value> = self
+            .nodes
+            .iter()
+            .map(|(n, node)| {
+                json!({
+                    "eip": n,
+                    "title": node.title,
+                    "status": node.status.map(|s| s.to_string()),
+                    "requires": node.requires,
+                })
+            })
+            .collect();
+
+        // the same cycle is found from each of its EIPs, so it's listed once,
+        // starting from its lowest
+        let cycles: BTreeSet<Vec<u64>> = self
+            .cycles()
+            .into_values()
+            .map(|mut cycle| {
+                cycle.pop();
+                let lowest = (0..cycle.len()).min_by_key(|i| cycle[*i]).unwrap();
+                cycle.rotate_left(lowest);
+                cycle.push(cycle[0]);
+                cycle
+            })
+            .collect();
+
+        json!({
+            "nodes": nodes,
+            "cycles": cycles,
+        })
+    }
+}
@@ -0,0 +1,58 @@
+#![allow(unused_variables)]
+#![allow(clippy::should_implement_trait)]
+mod body;
+mod config;
+mod ctx;
+mod eip;
+mod error;
+mod fix;
+mod graph;
+mod output;
+mod runner;
+mod validators;
+
+pub use config::{Config, FileConfig};
+pub use ctx::Context;
+pub use eip::{Category, Eip, Field, Preamble, Status, Type};
+pub use error::{Diagnostic, Error, Severity, Span};
+pub use fix::{fix, reorder_preamble};
+pub use graph::{Graph, Node};
+pub use output::Format;
+pub use runner::{Report, Runner};
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Validates the contents of a single EIP.
+pub fn validate_str(ctx: &Context, s: &str) -> Result<Eip, Vec<Diagnostic>> {
+    let crlf = crlf_offsets(s);
+    let s = normalize(s);
+    Eip::from_str(&ctx.with_source(&s), &s).map_err(|mut diagnostics| {
+        for d in diagnostics.iter_mut() {
+            d.span.denormalize(&crlf);
+        }
+        diagnostics
+    })
+}
+
+/// Reads and validates the EIP located at `path`.
+pub fn validate_path<P: AsRef<Path>>(
+    ctx: &Context,
+    path: P,
+) -> io::Result<Result<Eip, Vec<Diagnostic>>> {
+    Ok(validate_str(ctx, &fs::read_to_string(path)?))
+}
+
+pub(crate) fn normalize(s: &str) -> String {
+    s.replace("\r\n", "\n")
+}
+
+/// Returns the offsets, within the normalized `s`, of the newlines which were
+/// preceded by `\r`.
+pub(crate) fn crlf_offsets(s: &str) -> Vec<usize> {
+    s.match_indices("\r\n")
+        .enumerate()
+        .map(|(i, (p, _))| p - i)
+        .collect()
+}
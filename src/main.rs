@@ -1,27 +1,70 @@
-#![allow(unused_variables)]
-mod ctx;
-mod eip;
-mod error;
-mod runner;
-mod validators;
-
-use clap::{App, Arg};
-use runner::Runner;
+use chrono::NaiveDate;
+use clap::{App, AppSettings, Arg};
+use eipv::{Config, Error, Format, Graph, Runner, Severity};
 use std::process::exit;
 
 fn main() {
     let matches = App::new("eipv")
         .version("0.0.0")
         .about("Validate the structure of Ethereum Improvement Proposals")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(App::new("rules").about("List every rule along with its code and severity"))
+        .subcommand(
+            App::new("graph")
+                .about("Print the graph of EIPs and the EIPs they require")
+                .arg(
+                    Arg::new("path")
+                        .takes_value(true)
+                        .required(true)
+                        .about("Directory of EIPs"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .takes_value(true)
+                        .short('f')
+                        .long("format")
+                        .possible_values(&["dot", "json"])
+                        .default_value("dot")
+                        .about("Output format of the graph."),
+                ),
+        )
+        .subcommand(
+            App::new("fix")
+                .about("Fix mechanical errors, such as misplaced whitespace, in place")
+                .arg(
+                    Arg::new("path")
+                        .takes_value(true)
+                        .required(true)
+                        .about("Directory of EIPs or path to a specific EIP"),
+                )
+                .arg(
+                    Arg::new("skip")
+                        .takes_value(true)
+                        .short('s')
+                        .long("skip")
+                        .about("Skip the specified files."),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .about("Print the fixes as a unified diff instead of applying them."),
+                ),
+        )
         .arg(
             Arg::new("path")
                 .takes_value(true)
                 .required(true)
                 .about("Directory of EIPs or path to a specific EIP"),
         )
+        .arg(
+            Arg::new("fix")
+                .long("fix")
+                .about("Fix mechanical errors in place before validating."),
+        )
         .arg(
             Arg::new("ignore")
                 .takes_value(true)
+                .multiple_occurrences(true)
                 .short('i')
                 .long("ignore")
                 .about("Run the validation suite, ignoring the specified errors."),
@@ -33,18 +76,173 @@ fn main() {
                 .long("skip")
                 .about("Skip validation of the specified files."),
         )
+        .arg(
+            Arg::new("file-name-pattern")
+                .takes_value(true)
+                .long("file-name-pattern")
+                .about("Pattern EIP file names must follow, where `{}` is the EIP number [default: eip-{}.md]"),
+        )
+        .arg(
+            Arg::new("discussions-to-host")
+                .takes_value(true)
+                .long("discussions-to-host")
+                .about("Require discussions-to to be on the specified host, e.g. ethereum-magicians.org."),
+        )
+        .arg(
+            Arg::new("today")
+                .takes_value(true)
+                .long("today")
+                .about("Date to check dates against, in the form YYYY-MM-DD [default: the current date]"),
+        )
+        .arg(
+            Arg::new("deny")
+                .takes_value(true)
+                .long("deny")
+                .about("Report the specified errors as errors."),
+        )
+        .arg(
+            Arg::new("warn")
+                .takes_value(true)
+                .long("warn")
+                .about("Report the specified errors as warnings."),
+        )
+        .arg(
+            Arg::new("info")
+                .takes_value(true)
+                .long("info")
+                .about("Report the specified errors as informational notes."),
+        )
+        .arg(
+            Arg::new("deny-warnings")
+                .long("deny-warnings")
+                .about("Treat warnings as errors."),
+        )
+        .arg(
+            Arg::new("format")
+                .takes_value(true)
+                .short('f')
+                .long("format")
+                .possible_values(&["text", "json", "sarif", "github", "junit"])
+                .default_value("text")
+                .about("Output format of the validation report."),
+        )
         .get_matches();
 
-    let runner = Runner::new(
-        matches.value_of("path").unwrap(),
-        matches.value_of("ignore"),
-        matches.value_of("skip"),
-    );
+    if matches.subcommand_matches("rules").is_some() {
+        for e in Error::all() {
+            println!(
+                "{:<45} {:<8} {}",
+                e.code(),
+                e.default_severity(),
+                e.human_readable()
+            );
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("graph") {
+        let path = matches.value_of("path").unwrap();
+        let runner = Runner::new(path, None, None).and_then(|mut r| {
+            if let Some((_, config)) = Config::discover(path)? {
+                r.config(&config)?;
+            }
+            Ok(r)
+        });
+
+        let mut r = match runner {
+            Ok(r) => r,
+            Err(e) => {
+                println!("{}", e);
+                exit(1)
+            }
+        };
+        r.validate();
+
+        let graph = Graph::new(r.reports());
+        match matches.value_of("format").unwrap() {
+            "json" => println!(
+                "{}",
+                serde_json::to_string_pretty(&graph.to_json()).unwrap()
+            ),
+            _ => println!("{}", graph.to_dot()),
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("fix") {
+        let path = matches.value_of("path").unwrap();
+        let diff = Runner::new(path, None, matches.value_of("skip")).and_then(|mut r| {
+            if let Some((_, config)) = Config::discover(path)? {
+                r.config(&config)?;
+            }
+            r.fix(matches.is_present("dry-run"))
+        });
+
+        match diff {
+            Ok(diff) if matches.is_present("dry-run") => print!("{}", diff),
+            Ok(_) => (),
+            Err(e) => {
+                println!("{}", e);
+                exit(1)
+            }
+        }
+        return;
+    }
+
+    let ignore = matches
+        .values_of("ignore")
+        .map(|v| v.collect::<Vec<_>>().join(","));
+
+    let path = matches.value_of("path").unwrap();
+
+    let runner =
+        Runner::new(path, ignore.as_deref(), matches.value_of("skip")).and_then(|mut r| {
+            // the config is applied first, so that flags take precedence
+            let config = Config::discover(path)?.map(|(_, c)| c).unwrap_or_default();
+            r.config(&config)?;
+
+            for (arg, severity) in [
+                ("deny", Severity::Error),
+                ("warn", Severity::Warning),
+                ("info", Severity::Info),
+            ] {
+                if let Some(codes) = matches.value_of(arg) {
+                    r.set_severity(codes, severity)?;
+                }
+            }
+
+            if let Some(pattern) = matches.value_of("file-name-pattern") {
+                r.file_name_pattern(pattern);
+            }
+
+            if let Some(host) = matches.value_of("discussions-to-host") {
+                r.discussions_to_host(host);
+            }
+
+            if let Some(today) = matches.value_of("today") {
+                r.today(NaiveDate::parse_from_str(today, "%Y-%m-%d")?);
+            }
+
+            if matches.is_present("deny-warnings") {
+                r.deny_warnings();
+            }
+
+            if matches.is_present("fix") {
+                r.fix(false)?;
+            }
+
+            let format = match matches.occurrences_of("format") {
+                0 => config.format.as_deref().unwrap_or("text"),
+                _ => matches.value_of("format").unwrap(),
+            };
+
+            Ok((r, Format::from_str(format)?))
+        });
 
     match runner {
-        Ok(mut r) => {
+        Ok((mut r, format)) => {
             r.validate();
-            println!("{}", r);
+            println!("{}", format.render(&r));
 
             if r.invalid() != 0 {
                 exit(1)
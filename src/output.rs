@@ -0,0 +1,331 @@
+use crate::eip::Preamble;
+use crate::error::{Diagnostic, Error, Severity};
+use crate::runner::Runner;
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::env;
+use std::path::{Component, Path};
+use url::Url;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Sarif,
+    Github,
+    Junit,
+}
+
+impl Format {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "sarif" => Ok(Self::Sarif),
+            "github" => Ok(Self::Github),
+            "junit" => Ok(Self::Junit),
+            _ => Err(anyhow!("unknown format")),
+        }
+    }
+
+    pub fn render(&self, runner: &Runner) -> String {
+        match self {
+            Self::Text => runner.to_string(),
+            Self::Json => serde_json::to_string_pretty(&json(runner)).unwrap(),
+            Self::Sarif => serde_json::to_string_pretty(&sarif(runner)).unwrap(),
+            Self::Github => github(runner),
+            Self::Junit => junit(runner),
+        }
+    }
+}
+
+fn json(runner: &Runner) -> Value {
+    let files: Vec<Value> = runner
+        .reports()
+        .iter()
+        .map(|r| {
+            json!({
+                "file": r.file_name,
+                "valid": runner.is_valid(r),
+                "preamble": r.eip.as_ref().map(|e| preamble(&e.preamble)),
+                "diagnostics": r.diagnostics.iter().map(diagnostic).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+
+    json!({
+        "files": files,
+        "summary": {
+            "valid": runner.valid,
+            "invalid": runner.invalid,
+            "status": {
+                "draft": runner.draft,
+                "review": runner.review,
+                "last_call": runner.last_call,
+                "final": runner.final_,
+                "stagnant": runner.stagnant,
+                "withdrawn": runner.withdrawn,
+                "living": runner.living,
+            },
+            "type": {
+                "standards": runner.standards,
+                "informational": runner.informational,
+                "meta": runner.meta,
+            },
+            "category": {
+                "core": runner.core,
+                "networking": runner.networking,
+                "interface": runner.interface,
+                "erc": runner.erc,
+            },
+        },
+    })
+}
+
+fn sarif(runner: &Runner) -> Value {
+    let rules = Error::all();
+
+    let rule_index = |e: &Error| rules.iter().position(|r| r.code() == e.code());
+
+    let results: Vec<Value> = runner
+        .reports()
+        .iter()
+        .flat_map(|r| r.diagnostics.iter().map(move |d| (r, d)))
+        .map(|(r, d)| {
+            json!({
+                "ruleId": d.error.code(),
+                "ruleIndex": rule_index(&d.error),
+                "level": level(d.severity),
+                "message": { "text": d.error.to_string() },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": uri(&r.path) },
+                        "region": {
+                            "startLine": d.span.line,
+                            "startColumn": d.span.column,
+                            "byteOffset": d.span.start,
+                            "byteLength": d.span.end - d.span.start,
+                        },
+                    },
+                }],
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "eipv",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules.iter().map(|e| json!({
+                        "id": e.code(),
+                        "shortDescription": { "text": e.human_readable() },
+                        "defaultConfiguration": { "level": level(e.default_severity()) },
+                    })).collect::<Vec<_>>(),
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+/// Emits a GitHub Actions workflow command for each diagnostic, so failures
+/// are shown as annotations on the pull request.
+fn github(runner: &Runner) -> String {
+    let mut out = String::new();
+
+    for r in runner.reports() {
+        for d in r.diagnostics.iter() {
+            let command = match d.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Info => "notice",
+            };
+
+            out.push_str(&format!(
+                "::{} file={},line={},col={}::{}\n",
+                command,
+                escape_property(&workspace_path(&r.path)),
+                d.span.line,
+                d.span.column,
+                escape_data(&d.error.to_string()),
+            ));
+        }
+    }
+
+    out.push('\n');
+    out.push_str(&runner.summary());
+    out
+}
+
+fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Renders a JUnit XML report, where each file is a test case and each
+/// diagnostic a failure.
+fn junit(runner: &Runner) -> String {
+    let tests = runner.reports().len() + runner.skipped().len();
+    let skipped = runner.skipped().len();
+
+    let mut cases = String::new();
+
+    for r in runner.reports() {
+        cases.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"eipv\" file=\"{}\"",
+            escape_xml(&r.file_name),
+            escape_xml(&uri(&r.path)),
+        ));
+
+        if r.diagnostics.is_empty() {
+            cases.push_str("/>\n");
+            continue;
+        }
+
+        // only failing diagnostics are failures, the rest are logged
+        let (failures, others): (Vec<_>, Vec<_>) = r
+            .diagnostics
+            .iter()
+            .partition(|d| runner.context().fails(d));
+
+        cases.push_str(">\n");
+        for d in failures {
+            cases.push_str(&format!(
+                "      <failure type=\"{}\" message=\"{}\">{}</failure>\n",
+                d.error.code(),
+                escape_xml(&d.error.to_string()),
+                escape_xml(&format!("{}:{}", r.file_name, d)),
+            ));
+        }
+        if !others.is_empty() {
+            let out: Vec<String> = others
+                .iter()
+                .map(|d| escape_xml(&format!("{}:{}", r.file_name, d)))
+                .collect();
+            cases.push_str(&format!(
+                "      <system-out>{}</system-out>\n",
+                out.join("\n")
+            ));
+        }
+        cases.push_str("    </testcase>\n");
+    }
+
+    for path in runner.skipped() {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        cases.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"eipv\" file=\"{}\">\n      <skipped/>\n    </testcase>\n",
+            escape_xml(&name),
+            escape_xml(&uri(path)),
+        ));
+    }
+
+    let attrs = format!(
+        "tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\"",
+        tests, runner.invalid, skipped
+    );
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"eipv\" {attrs}>\n  <testsuite name=\"{name}\" {attrs}>\n{cases}  </testsuite>\n</testsuites>",
+        attrs = attrs,
+        name = escape_xml(runner.path),
+        cases = cases,
+    )
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn level(s: Severity) -> &'static str {
+    match s {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "note",
+    }
+}
+
+/// Formats a path as a URI reference, relative unless the path is absolute.
+fn uri(path: &Path) -> String {
+    if path.is_absolute() {
+        if let Ok(url) = Url::from_file_path(path) {
+            return url.to_string();
+        }
+    }
+
+    slash_path(path)
+}
+
+/// Returns `path` relative to `GITHUB_WORKSPACE`, or else the current
+/// directory, which is how annotations are matched to repository files.
+fn workspace_path(path: &Path) -> String {
+    let relative = env::var_os("GITHUB_WORKSPACE")
+        .map(Into::into)
+        .into_iter()
+        .chain(env::current_dir().ok())
+        .find_map(|dir| path.strip_prefix(dir).ok());
+
+    slash_path(relative.unwrap_or(path))
+}
+
+/// Joins the components of `path` with `/`, leaving out any `.`.
+fn slash_path(path: &Path) -> String {
+    path.components()
+        .filter(|c| *c != Component::CurDir)
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn preamble(p: &Preamble) -> Value {
+    // only successfully parsed fields are included, everything else is null
+    fn ok<T>(v: &Option<anyhow::Result<T>>) -> Option<&T> {
+        v.as_ref().and_then(|v| v.as_ref().ok())
+    }
+
+    fn date(d: &chrono::NaiveDate) -> String {
+        d.format("%Y-%m-%d").to_string()
+    }
+
+    json!({
+        "eip": ok(&p.eip),
+        "title": ok(&p.title),
+        "description": ok(&p.description),
+        "author": ok(&p.author),
+        "discussions-to": ok(&p.discussions_to).map(|u| u.as_str()),
+        "status": ok(&p.status).map(|s| s.to_string()),
+        "last-call-deadline": ok(&p.last_call_deadline).map(date),
+        "type": ok(&p.ty).map(|t| t.to_string()),
+        "category": ok(&p.category).map(|c| c.to_string()),
+        "created": ok(&p.created).map(date),
+        "updated": ok(&p.updated).map(|u| u.iter().map(date).collect::<Vec<_>>()),
+        "requires": ok(&p.requires),
+        "withdrawal-reason": ok(&p.withdrawal_reason),
+    })
+}
+
+fn diagnostic(d: &Diagnostic) -> Value {
+    json!({
+        "code": d.error.code(),
+        "severity": d.severity.to_string(),
+        "message": d.error.to_string(),
+        "line": d.span.line,
+        "column": d.span.column,
+        "start": d.span.start,
+        "end": d.span.end,
+    })
+}
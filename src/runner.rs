@@ -1,50 +1,75 @@
+use crate::body;
+use crate::config::{Config, CONFIG_FILE_NAME};
 use crate::ctx::Context;
 use crate::eip::{Category, Eip, Status, Type};
-use crate::error::Error;
+use crate::error::{Diagnostic, Error, Reporter, Severity};
+use crate::fix;
+use crate::graph::Graph;
+use crate::validators;
 
 use anyhow::Result;
+use chrono::NaiveDate;
+use similar::TextDiff;
+use std::collections::HashMap;
 use std::fmt;
 use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The outcome of validating a single file.
+#[derive(Debug)]
+pub struct Report {
+    pub path: PathBuf,
+    pub file_name: String,
+    /// The contents of the file, with CRLF line endings normalized to LF.
+    pub source: String,
+    pub eip: Option<Eip>,
+    pub diagnostics: Vec<Diagnostic>,
+    // offsets of the newlines in `source` which were CRLF on disk
+    crlf: Vec<usize>,
+}
 
 #[derive(Debug, Default)]
 pub struct Runner<'a> {
-    path: &'a str,
+    pub(crate) path: &'a str,
     ctx: Context,
-    errors: Vec<(String, Vec<Error>)>,
+    pub(crate) reports: Vec<Report>,
+    pub(crate) skipped: Vec<PathBuf>,
 
     // validity count
-    valid: u64,
-    invalid: u64,
+    pub(crate) valid: u64,
+    pub(crate) invalid: u64,
 
     // statuses count
-    draft: u64,
-    review: u64,
-    last_call: u64,
-    final_: u64,
-    stagnant: u64,
-    withdrawn: u64,
-    living: u64,
+    pub(crate) draft: u64,
+    pub(crate) review: u64,
+    pub(crate) last_call: u64,
+    pub(crate) final_: u64,
+    pub(crate) stagnant: u64,
+    pub(crate) withdrawn: u64,
+    pub(crate) living: u64,
 
     // types count
-    standards: u64,
-    meta: u64,
-    informational: u64,
+    pub(crate) standards: u64,
+    pub(crate) meta: u64,
+    pub(crate) informational: u64,
 
     // categories count
-    core: u64,
-    erc: u64,
-    interface: u64,
-    networking: u64,
+    pub(crate) core: u64,
+    pub(crate) erc: u64,
+    pub(crate) interface: u64,
+    pub(crate) networking: u64,
 }
 
 impl<'a> Runner<'a> {
     pub fn new(path: &'a str, ignore: Option<&'a str>, skip: Option<&'a str>) -> Result<Self> {
-        let mut ret = Self::default();
-        ret.path = path;
+        let mut ret = Self {
+            path,
+            ..Default::default()
+        };
 
         if let Some(ignore) = ignore {
             for i in ignore.split(',') {
-                Error::from_str(i).and_then(|v| Ok(ret.ctx.ignore(v)))?;
+                ret.ctx.ignore(Error::from_str(i)?);
             }
         }
 
@@ -57,6 +82,35 @@ impl<'a> Runner<'a> {
         Ok(ret)
     }
 
+    pub fn config(&mut self, config: &Config) -> Result<()> {
+        config.apply(&mut self.ctx)
+    }
+
+    /// Overrides the severity of each comma-separated error code in `codes`.
+    pub fn set_severity(&mut self, codes: &str, severity: Severity) -> Result<()> {
+        for c in codes.split(',') {
+            self.ctx.set_severity(Error::from_str(c)?, severity);
+        }
+
+        Ok(())
+    }
+
+    pub fn deny_warnings(&mut self) {
+        self.ctx.deny_warnings();
+    }
+
+    pub fn file_name_pattern(&mut self, pattern: &str) {
+        self.ctx.set_file_name_pattern(pattern);
+    }
+
+    pub fn discussions_to_host(&mut self, host: &str) {
+        self.ctx.set_discussions_to_host(host);
+    }
+
+    pub fn today(&mut self, today: NaiveDate) {
+        self.ctx.set_today(today);
+    }
+
     pub fn invalid(&self) -> u64 {
         self.invalid
     }
@@ -67,92 +121,357 @@ impl<'a> Runner<'a> {
                 if m.is_file() {
                     self.validate_single(self.path)
                 } else {
-                    let dir = fs::read_dir(self.path).expect("unable to read dir");
-                    for entry in dir {
-                        if let Ok(entry) = entry {
-                            self.validate_single(entry.path())
-                        }
+                    // sorted, so reports don't depend on the order of the
+                    // filesystem
+                    let mut paths: Vec<PathBuf> = fs::read_dir(self.path)
+                        .expect("unable to read dir")
+                        .flatten()
+                        .filter(|e| e.file_name() != CONFIG_FILE_NAME)
+                        .map(|e| e.path())
+                        .filter(|p| p.is_file())
+                        .collect();
+                    paths.sort();
+
+                    for path in paths {
+                        self.validate_single(path)
                     }
+
+                    // only a whole directory gives the context to check
+                    // references between EIPs
+                    self.validate_requires();
+                    self.validate_links();
                 }
             }
             Err(e) => panic!("{}", e),
         }
+
+        let mut reports = std::mem::take(&mut self.reports);
+        for report in reports.iter_mut() {
+            // spans were found in the normalized source, but point into the
+            // file on disk
+            for d in report.diagnostics.iter_mut() {
+                d.span.denormalize(&report.crlf);
+            }
+            self.count(report);
+        }
+        self.reports = reports;
     }
 
-    fn validate_single<P: AsRef<std::path::Path> + Clone>(&mut self, path: P) {
-        if !self
-            .ctx
-            .should_skip(path.as_ref().file_name().unwrap().to_str().unwrap())
-        {
-            let res: Result<Eip, Vec<Error>> = Eip::from_str(
-                &self.ctx,
-                &fs::read_to_string(path.clone())
-                    .unwrap()
-                    // normalize newlines
-                    .replace("\r\n", "\n"),
-            );
-            self.count(
-                res,
-                path.as_ref()
-                    .file_name()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
+    /// Fixes the mechanical errors of every file which isn't skipped, and
+    /// returns the changes as a unified diff. Files are only rewritten if
+    /// `dry_run` isn't set.
+    pub fn fix(&self, dry_run: bool) -> Result<String> {
+        let mut paths = if fs::metadata(self.path)?.is_file() {
+            vec![PathBuf::from(self.path)]
+        } else {
+            fs::read_dir(self.path)?
+                .flatten()
+                .filter(|e| e.file_name() != CONFIG_FILE_NAME)
+                .map(|e| e.path())
+                .filter(|p| p.is_file())
+                .collect()
+        };
+        paths.sort();
+
+        let mut diff = String::new();
+
+        for path in paths {
+            let name = path.file_name().unwrap().to_string_lossy();
+            if self.ctx.should_skip(&name) {
+                continue;
+            }
+
+            let src = fs::read_to_string(&path)?;
+            let fixed = fix::fix(&src);
+            if fixed == src {
+                continue;
+            }
+
+            let header = path.to_string_lossy();
+            diff.push_str(
+                &TextDiff::from_lines(&src, &fixed)
+                    .unified_diff()
+                    .header(&header, &header)
                     .to_string(),
             );
+
+            if !dry_run {
+                fs::write(&path, fixed)?;
+            }
         }
+
+        Ok(diff)
     }
 
-    fn count(&mut self, res: Result<Eip, Vec<Error>>, file_name: String) {
-        match res {
-            Ok(eip) => {
-                self.valid += 1;
+    pub fn context(&self) -> &Context {
+        &self.ctx
+    }
+
+    pub fn reports(&self) -> &[Report] {
+        &self.reports
+    }
 
-                match eip.preamble.status {
-                    Some(Ok(Status::Draft)) => self.draft += 1,
-                    Some(Ok(Status::Review)) => self.review += 1,
-                    Some(Ok(Status::LastCall)) => self.last_call += 1,
-                    Some(Ok(Status::Final)) => self.final_ += 1,
-                    Some(Ok(Status::Stagnant)) => self.stagnant += 1,
-                    Some(Ok(Status::Withdrawn)) => self.withdrawn += 1,
-                    Some(Ok(Status::Living)) => self.living += 1,
-                    _ => (),
+    pub fn skipped(&self) -> &[PathBuf] {
+        &self.skipped
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        format!(
+            "draft: {}, review: {}, last_call: {}, final: {}, stagnant: {}, withdrawn: {}, living: {}\nvalid: {}, invalid: {}",
+            self.draft,
+            self.review,
+            self.last_call,
+            self.final_,
+            self.stagnant,
+            self.withdrawn,
+            self.living,
+            self.valid,
+            self.invalid
+        )
+    }
+
+    fn validate_single<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref().to_path_buf();
+        let file_name = path.file_name().unwrap().to_str().unwrap().to_string();
+
+        if !self.ctx.should_skip(&file_name) {
+            let original = fs::read_to_string(&path).expect("unable to read file");
+            let src = crate::normalize(&original);
+            let ctx = self.ctx.for_file(&file_name, &src);
+            let (eip, diagnostics) = match Eip::parse(&ctx, &src) {
+                Ok((eip, diagnostics)) => (Some(eip), diagnostics),
+                Err(diagnostics) => (None, diagnostics),
+            };
+
+            let mut report = Report {
+                path,
+                file_name,
+                source: src,
+                eip,
+                diagnostics,
+                crlf: crate::crlf_offsets(&original),
+            };
+
+            self.validate_file_name(&mut report);
+            self.reports.push(report);
+        } else {
+            self.skipped.push(path);
+        }
+    }
+
+    /// Returns whether the report is free of failing diagnostics.
+    pub fn is_valid(&self, report: &Report) -> bool {
+        report.eip.is_some() && !report.diagnostics.iter().any(|d| self.ctx.fails(d))
+    }
+
+    /// Checks the file name follows the configured pattern and matches the
+    /// EIP number in the preamble.
+    fn validate_file_name(&self, report: &mut Report) {
+        let ctx = self.ctx.for_file(&report.file_name, &report.source);
+        let mut errors = Reporter::new(&ctx, &report.source);
+        let pattern = self.ctx.file_name_pattern();
+
+        // a malformed name is reported against the initial delimiter, like
+        // missing fields, and a mismatch against the EIP number
+        match validators::file_name(pattern, &report.file_name) {
+            Ok(n) => {
+                if let Some(preamble) = report.eip.as_ref().map(|e| &e.preamble) {
+                    if let (Some(Ok(eip)), Some(field)) = (&preamble.eip, preamble.field("eip")) {
+                        if *eip != n {
+                            let expected = pattern.replace("{}", &eip.to_string());
+                            errors.report(
+                                Error::FileNameMismatch(expected),
+                                field.value_range.clone(),
+                            );
+                        }
+                    }
                 }
+            }
+            Err(e) => errors.report(e, 0..3),
+        }
+
+        report.diagnostics.extend(errors.into_inner());
+    }
+
+    /// Checks that every required EIP is among those validated, that its
+    /// status is compatible with the requiring EIP's, and that no EIPs
+    /// require each other in a cycle.
+    fn validate_requires(&mut self) {
+        // the status of each EIP, if known
+        let mut index: HashMap<u64, Option<Status>> = self
+            .reports
+            .iter()
+            .filter_map(|r| {
+                let preamble = &r.eip.as_ref()?.preamble;
+                let n = *preamble.eip.as_ref()?.as_ref().ok()?;
+                let status = preamble.status.as_ref().and_then(|s| s.as_ref().ok());
+                Some((n, status.copied()))
+            })
+            .collect();
+
+        // skipped files and those whose preamble couldn't be parsed still
+        // exist, even if their status is unknown
+        let pattern = self.ctx.file_name_pattern();
+        let names = self
+            .skipped
+            .iter()
+            .filter_map(|p| p.file_name()?.to_str())
+            .chain(self.reports.iter().map(|r| r.file_name.as_str()));
+        for name in names {
+            if let Ok(n) = validators::file_name(pattern, name) {
+                index.entry(n).or_insert(None);
+            }
+        }
 
-                match eip.preamble.ty {
-                    Some(Ok(Type::Standards)) => self.standards += 1,
-                    Some(Ok(Type::Informational)) => self.informational += 1,
-                    Some(Ok(Type::Meta)) => self.meta += 1,
-                    _ => (),
+        for report in self.reports.iter_mut() {
+            let preamble = match &report.eip {
+                Some(eip) => &eip.preamble,
+                None => continue,
+            };
+
+            let (requires, range) = match (&preamble.requires, preamble.field("requires")) {
+                (Some(Ok(requires)), Some(field)) => (requires, field.value_range.clone()),
+                _ => continue,
+            };
+
+            let ctx = self.ctx.for_file(&report.file_name, &report.source);
+            let mut errors = Reporter::new(&ctx, &report.source);
+
+            for n in requires.iter() {
+                let required = match index.get(n) {
+                    Some(status) => status,
+                    None => {
+                        errors.report(Error::RequiresUnknownEip(*n), range.clone());
+                        continue;
+                    }
+                };
+
+                if let (Some(Ok(eip)), Some(Ok(status)), Some(required)) =
+                    (&preamble.eip, &preamble.status, required)
+                {
+                    if !status.may_require(required) {
+                        let e = Error::RequiresStatus {
+                            eip: *eip,
+                            status: *status,
+                            requires: *n,
+                            requires_status: *required,
+                        };
+                        errors.report(e, range.clone());
+                    }
                 }
+            }
+
+            report.diagnostics.extend(errors.into_inner());
+        }
+
+        let cycles = Graph::new(&self.reports).cycles();
+        if cycles.is_empty() {
+            return;
+        }
 
-                match eip.preamble.category {
-                    Some(Ok(Category::Core)) => self.core += 1,
-                    Some(Ok(Category::Networking)) => self.networking += 1,
-                    Some(Ok(Category::Interface)) => self.interface += 1,
-                    Some(Ok(Category::Erc)) => self.erc += 1,
-                    _ => (),
+        // each EIP in a cycle is given the shortest one through itself
+        for report in self.reports.iter_mut() {
+            let preamble = match &report.eip {
+                Some(eip) => &eip.preamble,
+                None => continue,
+            };
+
+            let (n, range) = match (&preamble.eip, preamble.field("requires")) {
+                (Some(Ok(n)), Some(field)) => (*n, field.value_range.clone()),
+                _ => continue,
+            };
+
+            let ctx = self.ctx.for_file(&report.file_name, &report.source);
+            let mut errors = Reporter::new(&ctx, &report.source);
+
+            if let Some(cycle) = cycles.get(&n) {
+                errors.report(Error::RequiresCycle(cycle.clone()), range);
+            }
+
+            report.diagnostics.extend(errors.into_inner());
+        }
+    }
+
+    /// Checks that relative links to other EIPs point to files that exist.
+    fn validate_links(&mut self) {
+        let pattern = self.ctx.file_name_pattern();
+
+        for report in self.reports.iter_mut() {
+            let eip = match &report.eip {
+                Some(eip) => eip,
+                None => continue,
+            };
+
+            let offset = report.source.len() - eip.body.len();
+            let dir = report.path.parent().unwrap_or_else(|| Path::new(""));
+            let ctx = self.ctx.for_file(&report.file_name, &report.source);
+            let mut errors = Reporter::new(&ctx, &report.source);
+
+            for link in body::links(&eip.body) {
+                let path = link.url.split('#').next().unwrap_or_default();
+                let name = path.strip_prefix("./").unwrap_or(path);
+
+                // only links to other EIPs are checked, not those to assets
+                if validators::file_name(pattern, name).is_ok() && !dir.join(name).is_file() {
+                    errors.report(
+                        Error::MissingLinkedEip(name.to_string()),
+                        offset + link.range.start..offset + link.range.end,
+                    );
                 }
             }
-            Err(e) => {
+
+            report.diagnostics.extend(errors.into_inner());
+        }
+    }
+
+    fn count(&mut self, report: &Report) {
+        let eip = match &report.eip {
+            Some(eip) if self.is_valid(report) => eip,
+            _ => {
                 self.invalid += 1;
-                self.errors.push((file_name, e));
+                return;
             }
+        };
+
+        self.valid += 1;
+
+        match eip.preamble.status {
+            Some(Ok(Status::Draft)) => self.draft += 1,
+            Some(Ok(Status::Review)) => self.review += 1,
+            Some(Ok(Status::LastCall)) => self.last_call += 1,
+            Some(Ok(Status::Final)) => self.final_ += 1,
+            Some(Ok(Status::Stagnant)) => self.stagnant += 1,
+            Some(Ok(Status::Withdrawn)) => self.withdrawn += 1,
+            Some(Ok(Status::Living)) => self.living += 1,
+            _ => (),
+        }
+
+        match eip.preamble.ty {
+            Some(Ok(Type::Standards)) => self.standards += 1,
+            Some(Ok(Type::Informational)) => self.informational += 1,
+            Some(Ok(Type::Meta)) => self.meta += 1,
+            _ => (),
+        }
+
+        match eip.preamble.category {
+            Some(Ok(Category::Core)) => self.core += 1,
+            Some(Ok(Category::Networking)) => self.networking += 1,
+            Some(Ok(Category::Interface)) => self.interface += 1,
+            Some(Ok(Category::Erc)) => self.erc += 1,
+            _ => (),
         }
     }
 }
 
 impl<'a> fmt::Display for Runner<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for error in self.errors.iter() {
-            let eip = error.0.clone();
-            for error in error.1.iter() {
-                write!(f, "{}:\t{}\n", eip, error.human_readable())?;
+        for report in self.reports.iter() {
+            for diagnostic in report.diagnostics.iter() {
+                writeln!(f, "{}:{}", report.file_name, diagnostic)?;
             }
         }
 
-        write!(f, "\n")?;
-        write!(f, "draft: {}, review: {}, last_call: {}, final: {}, stagnant: {}, withdrawn: {}, living: {}\n", self.draft, self.review, self.last_call, self.final_, self.stagnant, self.withdrawn, self.living)?;
-        write!(f, "valid: {}, invalid: {}", self.valid, self.invalid)
+        writeln!(f)?;
+        write!(f, "{}", self.summary())
     }
 }
@@ -5,37 +5,44 @@ use chrono::NaiveDate;
 use regex::Regex;
 use url::Url;
 
-const TITLE_MAX_LEN: usize = 44;
-const DESCRIPTION_MAX_LEN: usize = 140;
+pub const TITLE_MAX_LEN: usize = 44;
+pub const DESCRIPTION_MAX_LEN: usize = 140;
+pub const FILE_NAME_PATTERN: &str = "eip-{}.md";
 
 pub fn preamble(s: &str) -> Result<(&str, &str)> {
     match s.starts_with("---\n") {
         false => Err(Error::StartDelimiterMissing),
         true => match s[4..].find("---\n") {
-            Some(idx) => Ok((&s[4..idx + 4], &s[idx + 4..])),
+            Some(idx) => Ok((&s[4..idx + 4], &s[idx + 8..])),
             None => Err(Error::EndDelimiterMissing),
         },
     }
 }
 
 pub fn eip(s: &str) -> Result<u64> {
-    Ok(s.parse::<u64>().map_err(|_| Error::MalformedEipNumber)?)
+    s.parse::<u64>().map_err(|_| Error::MalformedEipNumber)
 }
 
 pub fn title(s: &str) -> Result<String> {
-    if TITLE_MAX_LEN < s.len() {
-        return Err(Error::TitleExceedsMaxLength);
-    }
+    Ok(s.to_string())
+}
 
-    return Ok(s.to_string());
+pub fn title_length(s: &str, max: usize) -> Option<Error> {
+    match max < s.len() {
+        true => Some(Error::TitleExceedsMaxLength(max)),
+        false => None,
+    }
 }
 
 pub fn description(s: &str) -> Result<String> {
-    if DESCRIPTION_MAX_LEN < s.len() {
-        return Err(Error::DescriptionExceedsMaxLength);
-    }
+    Ok(s.to_string())
+}
 
-    return Ok(s.to_string());
+pub fn description_length(s: &str, max: usize) -> Option<Error> {
+    match max < s.len() {
+        true => Some(Error::DescriptionExceedsMaxLength(max)),
+        false => None,
+    }
 }
 
 pub fn author(s: &str) -> Result<Vec<String>> {
@@ -43,7 +50,31 @@ pub fn author(s: &str) -> Result<Vec<String>> {
 }
 
 pub fn discussions_to(s: &str) -> Result<Url> {
-    Ok(Url::parse(s).map_err(|_| Error::MalformedDiscussionsTo)?)
+    Url::parse(s).map_err(|_| Error::MalformedDiscussionsTo)
+}
+
+/// Checks where discussions take place: never on a pull request, always over
+/// https and, if `host` is given, only on that host or its subdomains.
+pub fn discussions_to_rules(url: &Url, host: Option<&str>) -> Vec<Error> {
+    let mut errors = vec![];
+
+    let segments: Vec<&str> = url.path_segments().map(|s| s.collect()).unwrap_or_default();
+    if url.host_str() == Some("github.com") && segments.get(2) == Some(&"pull") {
+        errors.push(Error::DiscussionsToPullRequest);
+    }
+
+    if url.scheme() != "https" {
+        errors.push(Error::DiscussionsToInsecure);
+    }
+
+    if let Some(host) = host {
+        let actual = url.host_str().unwrap_or_default();
+        if actual != host && !actual.ends_with(&format!(".{}", host)) {
+            errors.push(Error::DiscussionsToHost(host.to_string()));
+        }
+    }
+
+    errors
 }
 
 pub fn status(s: &str) -> Result<Status> {
@@ -81,27 +112,63 @@ pub fn requires(s: &str) -> Result<Vec<u64>> {
     validate_csv(s, validate_eip)
 }
 
+/// Checks the required EIPs are in ascending order.
+pub fn requires_order(s: &str) -> Option<Error> {
+    let eips: Vec<u64> = s.split(',').filter_map(|n| n.trim().parse().ok()).collect();
+
+    match eips.windows(2).any(|w| w[1] < w[0]) {
+        true => Some(Error::OutOfOrderEips),
+        false => None,
+    }
+}
+
 pub fn withdrawal_reason(s: &str) -> Result<String> {
     Ok(s.to_string())
 }
 
-fn validate_csv<T, F: Fn(&mut Vec<T>, &str) -> Result<()>>(s: &str, f: F) -> Result<Vec<T>> {
-    let csv: Vec<&str> = s.split(",").collect();
+/// Extracts the EIP number from a file name following `pattern`.
+pub fn file_name(pattern: &str, s: &str) -> Result<u64> {
+    let form = pattern.replace("{}", "N");
+    let (prefix, suffix) = pattern
+        .split_once("{}")
+        .ok_or_else(|| Error::MalformedFileName(form.clone()))?;
 
-    let mut acc = vec![];
+    let n = s
+        .strip_prefix(prefix)
+        .and_then(|s| s.strip_suffix(suffix))
+        .ok_or_else(|| Error::MalformedFileName(form.clone()))?;
 
-    for (i, x) in csv.iter().enumerate() {
+    // only plain digits, so the number maps back to a single file name
+    if n.is_empty() || n.starts_with('0') || !n.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(Error::MalformedFileName(form));
+    }
+
+    n.parse().map_err(|_| Error::MalformedFileName(form))
+}
+
+/// Checks comma-separated values are separated by exactly `, `.
+pub fn csv_spacing(s: &str) -> Option<Error> {
+    for (i, x) in s.split(',').enumerate() {
         // the first element never has whitespace, so check trailing whitespace
         // all other elements should have only one whitespace at n[0]
-        if (i == 0 && x.trim_start() != *x) || (i != 0 && x.len() > 2 && x.trim_start() != &x[1..])
-        {
-            return Err(Error::MissingSpaceAfterComma);
+        if (i == 0 && x.trim_start() != x) || (i != 0 && x.len() > 2 && x.trim_start() != &x[1..]) {
+            return Some(Error::MissingSpaceAfterComma);
         }
 
-        if x != &x.trim_end() {
-            return Err(Error::ExtraWhitespaceBeforeComma);
+        if x != x.trim_end() {
+            return Some(Error::ExtraWhitespaceBeforeComma);
         }
+    }
 
+    None
+}
+
+/// Parses each comma-separated value with `f`, whatever the whitespace around
+/// it, which `csv_spacing` checks instead.
+fn validate_csv<T, F: Fn(&mut Vec<T>, &str) -> Result<()>>(s: &str, f: F) -> Result<Vec<T>> {
+    let mut acc = vec![];
+
+    for x in s.split(',') {
         f(&mut acc, x.trim())?;
     }
 
@@ -111,18 +178,14 @@ fn validate_csv<T, F: Fn(&mut Vec<T>, &str) -> Result<()>>(s: &str, f: F) -> Res
 fn validate_eip(acc: &mut Vec<u64>, s: &str) -> Result<()> {
     match s.parse() {
         Ok(n) => {
-            if acc.len() != 0 && acc[acc.len() - 1] > n {
-                Err(Error::OutOfOrderEips)
-            } else {
-                acc.push(n);
-                Ok(())
-            }
+            acc.push(n);
+            Ok(())
         }
-        Err(e) => Err(Error::MalformedEipNumber),
+        Err(_) => Err(Error::MalformedEipNumber),
     }
 }
 
-fn validate_author<'a>(acc: &mut Vec<String>, s: &str) -> Result<()> {
+fn validate_author(acc: &mut Vec<String>, s: &str) -> Result<()> {
     let email_start = s.find('<');
     let email_end = s.find('>');
 
@@ -137,14 +200,11 @@ fn validate_author<'a>(acc: &mut Vec<String>, s: &str) -> Result<()> {
         return Err(Error::UnmatchedHandleDelimiter);
     }
 
-    if email_start.is_some() == true && handle_start.is_some() == true {
+    if email_start.is_some() && handle_start.is_some() {
         return Err(Error::AuthorHasEmailAndHandle);
     }
 
-    if email_start.is_some() {
-        let start = email_start.unwrap();
-        let end = email_end.unwrap();
-
+    if let (Some(start), Some(end)) = (email_start, email_end) {
         if end != s.len() - 1 {
             return Err(Error::TrailingInfoAfterEmail);
         }
@@ -155,10 +215,7 @@ fn validate_author<'a>(acc: &mut Vec<String>, s: &str) -> Result<()> {
         }
     }
 
-    if handle_start.is_some() {
-        let start = handle_start.unwrap();
-        let end = handle_end.unwrap();
-
+    if let (Some(start), Some(end)) = (handle_start, handle_end) {
         let re = Regex::new(r#"(^@[a-zA-Z0-9]+(?:-[a-zA-Z0-9]+)*$)"#).unwrap();
         if !re.is_match(&s[start + 1..end]) {
             return Err(Error::MalformedHandle);
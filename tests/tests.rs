@@ -1,7 +1,10 @@
 mod utils;
 
 use utils::{
-    test_fixture, test_fixture_exclude_output, test_fixture_valid, test_fixture_valid_custom,
+    test_dir, test_dir_args, test_file_name, test_fix, test_fix_dry_run, test_fixture,
+    test_fixture_args, test_fixture_exclude_output, test_fixture_format, test_fixture_valid,
+    test_fixture_valid_custom, test_fixture_valid_custom_args, test_github_workspace, test_graph,
+    test_rules,
 };
 
 #[test]
@@ -39,6 +42,32 @@ fn preamble_unknown_field() {
     test_fixture("preamble-unknown-field.md", "unknown preamble field");
 }
 
+#[test]
+fn preamble_duplicate_field() {
+    test_fixture(
+        "preamble-duplicate-field.md",
+        "preamble-duplicate-field.md:5:1: duplicate `title` field on line 5, first given on line 3",
+    );
+}
+
+#[test]
+fn preamble_out_of_order_field() {
+    test_fixture(
+        "preamble-out-of-order-field.md",
+        "preamble-out-of-order-field.md:4:1: `title` field should come after `eip`",
+    );
+}
+
+#[test]
+fn reorder_preamble() {
+    let src = std::fs::read_to_string("tests/fixtures/preamble-out-of-order-field.md").unwrap();
+    let fixed = eipv::reorder_preamble(&src);
+
+    assert!(fixed.starts_with("---\neip: 1\ntitle: A sample proposal\nauthor: John Doe"));
+    assert_eq!(fixed.len(), src.len());
+    assert!(eipv::validate_str(&eipv::Context::default(), &fixed).is_ok());
+}
+
 #[test]
 fn preamble_malformed_field() {
     test_fixture("preamble-malformed-field.md", "malformed field");
@@ -66,6 +95,27 @@ fn preamble_invalid_discussions_to() {
     test_fixture("preamble-invalid-discussions-to.md", "must be a URL");
 }
 
+#[test]
+fn preamble_discussions_to_rules() {
+    test_fixture(
+        "preamble-discussions-to-pull-request.md",
+        "preamble-discussions-to-pull-request.md:5:17: discussions-to can't point to a pull request",
+    );
+    test_fixture(
+        "preamble-discussions-to-http.md",
+        "discussions-to must use https",
+    );
+    test_fixture_args(
+        "valid.md",
+        &["--discussions-to-host", "ethereum-magicians.org"],
+        "discussions-to must be on `ethereum-magicians.org`",
+    );
+    test_fixture_valid_custom_args(
+        "preamble-discussions-to-magicians.md",
+        &["--discussions-to-host", "ethereum-magicians.org"],
+    );
+}
+
 #[test]
 fn preamble_status() {
     test_fixture_valid_custom("preamble-status-draft.md", "draft: 1");
@@ -105,6 +155,29 @@ fn preamble_last_call_deadline() {
     );
 }
 
+#[test]
+fn preamble_status_fields() {
+    test_fixture(
+        "preamble-missing-last-call-deadline.md",
+        "missing last-call-deadline field in preamble",
+    );
+    test_fixture(
+        "preamble-missing-withdrawal-reason.md",
+        "missing withdrawal-reason field in preamble",
+    );
+    test_fixture_args(
+        "preamble-unexpected-last-call-deadline.md",
+        &[],
+        "preamble-unexpected-last-call-deadline.md:7:21: warning: last-call-deadline is only used in Last Call",
+    );
+    test_fixture_valid("preamble-unexpected-last-call-deadline.md");
+    test_fixture_args(
+        "preamble-unexpected-withdrawal-reason.md",
+        &["--deny", "preamble-unexpected-withdrawal-reason"],
+        "valid: 0, invalid: 1",
+    );
+}
+
 #[test]
 fn preamble_created() {
     test_fixture("preamble-created-malformed.md", "malformed created");
@@ -115,6 +188,37 @@ fn preamble_updated() {
     test_fixture("preamble-updated-malformed.md", "malformed updated");
 }
 
+#[test]
+fn preamble_dates() {
+    test_fixture(
+        "preamble-updated-before-created.md",
+        "preamble-updated-before-created.md:10:10: updated dates must be after the created date",
+    );
+    test_fixture(
+        "preamble-updated-same-as-created.md",
+        "updated dates must be after the created date",
+    );
+    test_fixture(
+        "preamble-updated-out-of-order.md",
+        "updated dates must be in ascending order",
+    );
+    test_fixture(
+        "preamble-last-call-deadline-before-created.md",
+        "last-call-deadline must be after the created date",
+    );
+    test_fixture_args(
+        "valid.md",
+        &["--today", "2020-01-15"],
+        "valid.md:12:10: updated date is in the future",
+    );
+    test_fixture_args(
+        "valid.md",
+        &["--today", "2019-12-31"],
+        "valid.md:11:10: created date is in the future",
+    );
+    test_fixture_valid_custom_args("valid.md", &["--today", "2020-02-01"]);
+}
+
 #[test]
 fn preamble_requires() {
     test_fixture_valid("preamble-requires-single.md");
@@ -172,3 +276,413 @@ fn preamble_description_too_long() {
         "description exceeds max length",
     );
 }
+
+#[test]
+fn library_validate_path() {
+    let ctx = eipv::Context::default();
+
+    let eip = eipv::validate_path(&ctx, "tests/fixtures/valid.md")
+        .unwrap()
+        .unwrap();
+    assert_eq!(eip.preamble.field("title").unwrap().line, 3);
+    assert_eq!(eip.preamble.eip.unwrap().unwrap(), 1);
+
+    let errors = eipv::validate_path(&ctx, "tests/fixtures/preamble-missing-title.md")
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].error, eipv::Error::MissingTitleField);
+}
+
+#[test]
+fn preamble_error_location() {
+    test_fixture(
+        "preamble-trailing-whitespace.md",
+        "preamble-trailing-whitespace.md:6:14: trailing whitespace",
+    );
+    test_fixture(
+        "preamble-author-email-invalid.md",
+        "preamble-author-email-invalid.md:4:9: malformed email",
+    );
+}
+
+#[test]
+fn format_json() {
+    test_fixture_format("valid.md", "json", r#""title": "A sample proposal""#);
+    test_fixture_format("valid.md", "json", r#""valid": 1"#);
+    test_fixture_format(
+        "preamble-trailing-whitespace.md",
+        "json",
+        r#""message": "trailing whitespace""#,
+    );
+}
+
+#[test]
+fn format_sarif() {
+    test_fixture_format("valid.md", "sarif", r#""version": "2.1.0""#);
+    test_fixture_format("valid.md", "sarif", r#""results": []"#);
+    test_fixture_format(
+        "preamble-trailing-whitespace.md",
+        "sarif",
+        r#""uri": "tests/fixtures/preamble-trailing-whitespace.md""#,
+    );
+    test_fixture_format(
+        "preamble-trailing-whitespace.md",
+        "sarif",
+        r#""startLine": 6"#,
+    );
+}
+
+#[test]
+fn format_github() {
+    test_fixture_format(
+        "preamble-trailing-whitespace.md",
+        "github",
+        "::error file=tests/fixtures/preamble-trailing-whitespace.md,line=6,col=14::trailing whitespace",
+    );
+    test_github_workspace(
+        "preamble-trailing-whitespace.md",
+        "tests",
+        "::error file=fixtures/preamble-trailing-whitespace.md,line=6,col=14::",
+    );
+    test_fixture_format(
+        "preamble-missing-title.md",
+        "github",
+        "valid: 0, invalid: 1",
+    );
+}
+
+#[test]
+fn format_junit() {
+    test_fixture_format(
+        "preamble-missing-title.md",
+        "junit",
+        r#"<failure type="preamble-missing-title" message="missing title field in preamble">"#,
+    );
+    test_fixture_format(
+        "valid.md",
+        "junit",
+        r#"<testcase name="valid.md" classname="eipv" file="tests/fixtures/valid.md"/>"#,
+    );
+    test_fixture_args(
+        "valid.md",
+        &["--format", "junit", "--skip", "valid.md"],
+        r#"tests="1" failures="0" errors="0" skipped="1""#,
+    );
+}
+
+#[test]
+fn ignore_by_code() {
+    test_fixture_valid_custom_args(
+        "preamble-title-too-long.md",
+        &["--ignore", "preamble-title-max-length"],
+    );
+    test_fixture_valid_custom_args(
+        "preamble-description-too-long.md",
+        &["--ignore", "description_max_length"],
+    );
+}
+
+#[test]
+fn rules() {
+    test_rules("preamble-trailing-whitespace");
+    test_rules("preamble-title-max-length");
+}
+
+#[test]
+fn severity() {
+    test_fixture_args(
+        "preamble-trailing-whitespace.md",
+        &["--warn", "preamble-trailing-whitespace"],
+        "preamble-trailing-whitespace.md:6:14: warning: trailing whitespace",
+    );
+    test_fixture_valid_custom_args(
+        "preamble-trailing-whitespace.md",
+        &["--warn", "preamble-trailing-whitespace"],
+    );
+    test_fixture_valid_custom_args(
+        "preamble-trailing-whitespace.md",
+        &["--info", "preamble-trailing-whitespace"],
+    );
+    test_fixture_args(
+        "preamble-trailing-whitespace.md",
+        &["--warn", "preamble-trailing-whitespace", "--deny-warnings"],
+        "valid: 0, invalid: 1",
+    );
+    test_fixture_format(
+        "preamble-trailing-whitespace.md",
+        "json",
+        r#""severity": "error""#,
+    );
+}
+
+#[test]
+fn body_sections() {
+    test_fixture(
+        "body-missing-section.md",
+        "missing required section `Rationale`",
+    );
+    test_fixture(
+        "body-unknown-section.md",
+        "body-unknown-section.md:12:1: unknown section `Simple Summary`",
+    );
+    test_fixture("body-duplicate-section.md", "duplicate section `Rationale`");
+    test_fixture(
+        "body-out-of-order-section.md",
+        "section `Abstract` is out of order",
+    );
+    test_fixture_valid("body-code-block-heading.md");
+}
+
+#[test]
+fn body_abstract_too_long() {
+    test_fixture(
+        "body-abstract-too-long.md",
+        "abstract is 205 words, exceeding the max of 200 words",
+    );
+    test_fixture_valid("body-abstract-code.md");
+}
+
+#[test]
+fn body_copyright() {
+    test_fixture("body-copyright-missing.md", "missing `Copyright` section");
+    test_fixture(
+        "body-copyright-modified.md",
+        "copyright waiver must be `Copyright and related rights waived via CC0.`",
+    );
+    test_fixture(
+        "body-copyright-trailing-content.md",
+        "unexpected content after copyright waiver",
+    );
+    test_fixture_args(
+        "body-copyright-not-last.md",
+        &[],
+        "body-copyright-not-last.md:39:1: unexpected content after copyright waiver",
+    );
+    test_fixture_valid("body-copyright-no-link.md");
+}
+
+#[test]
+fn body_unmatched_bracket() {
+    test_fixture(
+        "body-unmatched-bracket.md",
+        "body-unmatched-bracket.md:19:27: unmatched `(`",
+    );
+    // a fence only closes on a run of the same character at least as long
+    test_fixture_valid("body-nested-fence.md");
+}
+
+#[test]
+fn file_name() {
+    test_file_name("eip-1.md", &[], "valid: 1, invalid: 0");
+    test_file_name(
+        "eip-2.md",
+        &[],
+        "eip-2.md:2:6: file name should be `eip-1.md` to match the EIP number",
+    );
+    test_file_name("erc-1.md", &[], "file name must be of the form `eip-N.md`");
+    test_file_name(
+        "erc-1.md",
+        &["--file-name-pattern", "erc-{}.md"],
+        "valid: 1, invalid: 0",
+    );
+}
+
+#[test]
+fn requires_exists() {
+    test_fixture("preamble-requires-self.md", "EIP can't require itself");
+    test_dir("requires", "eip-3.md:10:11: required EIP 4 doesn't exist");
+    test_dir("requires", "valid: 2, invalid: 1");
+    // EIP 1 has no trailing delimiter, but its file still exists
+    test_dir("requires-unparsed", "valid: 1, invalid: 1");
+    // a lint on the field doesn't stop the required EIPs being checked
+    test_dir_args(
+        "requires-lint",
+        &["--ignore", "preamble-whitespace-before-comma"],
+        "eip-3.md:10:11: required EIP 4 doesn't exist",
+    );
+}
+
+#[test]
+fn requires_status() {
+    test_dir(
+        "requires-status",
+        "eip-2.md:10:11: EIP 2 (Final) can't require EIP 1 (Draft)",
+    );
+    test_dir(
+        "requires-status",
+        "eip-5.md:11:11: EIP 5 (Last Call) can't require EIP 6 (Review)",
+    );
+    test_dir("requires-status", "valid: 5, invalid: 2");
+}
+
+#[test]
+fn requires_cycle() {
+    test_dir(
+        "requires-cycle",
+        "eip-1.md:10:11: circular requirement: 1 -> 3 -> 2 -> 1",
+    );
+    test_dir(
+        "requires-cycle",
+        "eip-2.md:10:11: circular requirement: 2 -> 1 -> 3 -> 2",
+    );
+    test_dir("requires-cycle", "valid: 1, invalid: 3");
+    // only the shortest cycle through each EIP is reported
+    test_dir(
+        "requires-dense",
+        "eip-5.md:10:11: circular requirement: 5 -> 1 -> 5\n",
+    );
+
+    let mut r = eipv::Runner::new("tests/fixtures/requires-dense", None, None).unwrap();
+    r.validate();
+    for report in r.reports() {
+        assert_eq!(report.diagnostics.len(), 1);
+    }
+}
+
+#[test]
+fn graph() {
+    test_graph("requires-cycle", "dot", "    4 -> 1;");
+    test_graph("requires-cycle", "json", r#""cycles": ["#);
+    // eip-2.md is skipped by the eipv.toml
+    test_graph(
+        "graph-config",
+        "dot",
+        "    1 [label=\"EIP-1: A sample proposal\"];\n}",
+    );
+}
+
+#[test]
+fn body_eip_links() {
+    test_fixture_args(
+        "body-absolute-eip-link.md",
+        &[],
+        "body-absolute-eip-link.md:20:23: link to EIP should be relative: `./eip-20.md`",
+    );
+    test_fixture_args(
+        "body-absolute-eip-link.md",
+        &[],
+        "link to EIP should be relative: `./eip-721.md#specification`",
+    );
+    // the site names the file after the EIP, not the configured pattern
+    test_fixture_args(
+        "body-absolute-eip-link.md",
+        &["--file-name-pattern", "erc-{}.md"],
+        "link to EIP should be relative: `./eip-20.md`",
+    );
+    test_fixture_valid("body-relative-eip-link.md");
+    test_dir(
+        "links",
+        "eip-1.md:20:46: linked file `eip-3.md` doesn't exist",
+    );
+    test_dir("links", "valid: 1, invalid: 1");
+}
+
+#[test]
+fn fix() {
+    test_fix_dry_run("preamble-fixable.md", "+requires: 4, 20");
+    test_fix_dry_run(
+        "preamble-fixable.md",
+        "+author: John Doe (@johndoe), Jane Doe (@janedoe)",
+    );
+
+    let fixed = test_fix("preamble-fixable.md", &["fix"], "");
+    assert!(fixed.starts_with("---\neip: 1\ntitle: A sample proposal\n"));
+
+    test_fix(
+        "preamble-fixable.md",
+        &["--fix", "--ignore", "file-name-malformed"],
+        "valid: 1, invalid: 0",
+    );
+}
+
+#[test]
+fn fix_preserves_crlf() {
+    let src = std::fs::read_to_string("tests/fixtures/preamble-fixable.md").unwrap();
+    let fixed = eipv::fix(&src.replace('\n', "\r\n"));
+
+    assert_eq!(fixed.matches('\n').count(), fixed.matches("\r\n").count());
+    assert!(eipv::validate_str(&eipv::Context::default(), &fixed).is_ok());
+}
+
+#[test]
+fn fix_preserves_mixed_line_endings() {
+    let src = std::fs::read_to_string("tests/fixtures/preamble-fixable.md").unwrap();
+    let end = src[3..].find("---\n").unwrap() + 7;
+    let (preamble, body) = src.split_at(end);
+    let fixed = eipv::fix(&(preamble.replace('\n', "\r\n") + body));
+
+    let (fixed_preamble, fixed_body) = fixed.split_at(fixed.len() - body.len());
+    assert_eq!(fixed_body, body);
+    assert_eq!(
+        fixed_preamble.matches('\n').count(),
+        fixed_preamble.matches("\r\n").count()
+    );
+}
+
+#[test]
+fn spans_point_into_crlf_source() {
+    let src = std::fs::read_to_string("tests/fixtures/preamble-trailing-whitespace.md").unwrap();
+    let crlf = src.replace('\n', "\r\n");
+    let ctx = eipv::Context::default();
+
+    let lf = eipv::validate_str(&ctx, &src).unwrap_err();
+    let spans = eipv::validate_str(&ctx, &crlf).unwrap_err();
+
+    assert_eq!(lf.len(), spans.len());
+    for (a, b) in lf.iter().zip(spans.iter()) {
+        assert_eq!((a.span.line, a.span.column), (b.span.line, b.span.column));
+        assert_eq!(
+            &src[a.span.start..a.span.end],
+            &crlf[b.span.start..b.span.end]
+        );
+    }
+}
+
+#[test]
+fn reports_are_sorted() {
+    let mut r = eipv::Runner::new("tests/fixtures/requires-status", None, None).unwrap();
+    r.validate();
+
+    let names: Vec<&str> = r.reports().iter().map(|r| r.file_name.as_str()).collect();
+    let mut sorted = names.clone();
+    sorted.sort_unstable();
+    assert_eq!(names, sorted);
+}
+
+#[test]
+fn config() {
+    test_dir("config", r#""invalid": 1"#);
+    test_dir("config", r#""severity": "warning""#);
+    test_dir_args(
+        "config",
+        &["--format", "text"],
+        "eip-2.md:6:9: unknown status",
+    );
+    test_dir_args(
+        "config",
+        &["--format", "text", "--deny", "preamble-title-max-length"],
+        "valid: 0, invalid: 2",
+    );
+    test_dir("config/eip-1.md", r#""valid": 1"#);
+}
+
+#[test]
+fn disable() {
+    test_fixture_valid("preamble-title-too-long-disabled.md");
+    // the comment doesn't count as content after the copyright waiver
+    test_fixture_valid("preamble-title-too-long-disabled-eof.md");
+    test_dir("disable", "eip-1.md:3:8: title exceeds max length");
+    test_dir("disable", "valid: 1, invalid: 1");
+    // the title is still parsed, even though it's too long
+    test_fixture_format(
+        "preamble-title-too-long-disabled.md",
+        "json",
+        r#""title": "A sample proposal whose title is too long"#,
+    );
+    test_graph(
+        "disable",
+        "dot",
+        "2 [label=\"EIP-2: A sample proposal whose",
+    );
+}
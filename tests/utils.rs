@@ -1,40 +1,145 @@
 use assert_cmd::Command;
 use predicates::prelude::{predicate::str::contains, PredicateBooleanExt};
 
+/// The fixtures aren't named after their EIP numbers, so file name checks are
+/// ignored unless a test specifically covers them.
+const IGNORE_FILE_NAME: &str = "file-name-malformed,file-name-mismatch";
+
+fn eipv(f: &str) -> Command {
+    let path = ["tests/fixtures", f].join("/");
+    let mut cmd = Command::cargo_bin("eipv").expect("eipv binary missing");
+    cmd.arg(path).arg("--ignore").arg(IGNORE_FILE_NAME);
+    cmd
+}
+
 pub fn test_fixture(f: &str, output: &str) {
-    let path = vec!["tests/fixtures", f].join("/");
-    Command::cargo_bin("eipv")
-        .expect("eipv binary missing")
-        .arg(path)
+    eipv(f)
         .assert()
         .stdout(contains(output))
         .stdout(contains(f).count(1));
 }
 
 pub fn test_fixture_exclude_output(f: &str, not: &str) {
-    let path = vec!["tests/fixtures", f].join("/");
+    eipv(f).assert().stdout(contains(not).not());
+}
+
+pub fn test_fixture_valid(f: &str) {
+    eipv(f).assert().stdout(contains("valid: 1, invalid: 0"));
+}
+
+pub fn test_fixture_valid_custom(f: &str, output: &str) {
+    eipv(f)
+        .assert()
+        .stdout(contains("valid: 1, invalid: 0"))
+        .stdout(contains(output));
+}
+
+pub fn test_fixture_format(f: &str, format: &str, output: &str) {
+    eipv(f)
+        .arg("--format")
+        .arg(format)
+        .assert()
+        .stdout(contains(output));
+}
+
+/// Runs eipv with the absolute path of `f`, where the GitHub workspace is
+/// `workspace` within the current directory.
+pub fn test_github_workspace(f: &str, workspace: &str, output: &str) {
+    let dir = std::env::current_dir().unwrap();
+    Command::cargo_bin("eipv")
+        .expect("eipv binary missing")
+        .arg(dir.join("tests/fixtures").join(f))
+        .args(["--format", "github", "--ignore", IGNORE_FILE_NAME])
+        .env("GITHUB_WORKSPACE", dir.join(workspace))
+        .assert()
+        .stdout(contains(output));
+}
+
+pub fn test_fixture_args(f: &str, args: &[&str], output: &str) {
+    eipv(f).args(args).assert().stdout(contains(output));
+}
+
+pub fn test_fixture_valid_custom_args(f: &str, args: &[&str]) {
+    eipv(f)
+        .args(args)
+        .assert()
+        .stdout(contains("valid: 1, invalid: 0"));
+}
+
+pub fn test_file_name(f: &str, args: &[&str], output: &str) {
+    let path = ["tests/fixtures/file-name", f].join("/");
     Command::cargo_bin("eipv")
         .expect("eipv binary missing")
         .arg(path)
+        .args(args)
         .assert()
-        .stdout(contains(not).not());
+        .stdout(contains(output));
 }
 
-pub fn test_fixture_valid(f: &str) {
-    let path = vec!["tests/fixtures", f].join("/");
+pub fn test_rules(code: &str) {
+    Command::cargo_bin("eipv")
+        .expect("eipv binary missing")
+        .arg("rules")
+        .assert()
+        .success()
+        .stdout(contains(code));
+}
+
+pub fn test_dir(dir: &str, output: &str) {
+    test_dir_args(dir, &[], output);
+}
+
+pub fn test_dir_args(dir: &str, args: &[&str], output: &str) {
+    let path = ["tests/fixtures", dir].join("/");
     Command::cargo_bin("eipv")
         .expect("eipv binary missing")
         .arg(path)
+        .args(args)
         .assert()
-        .stdout(contains("valid: 1, invalid: 0"));
+        .stdout(contains(output));
 }
 
-pub fn test_fixture_valid_custom(f: &str, output: &str) {
-    let path = vec!["tests/fixtures", f].join("/");
+pub fn test_graph(dir: &str, format: &str, output: &str) {
+    let path = ["tests/fixtures", dir].join("/");
     Command::cargo_bin("eipv")
         .expect("eipv binary missing")
+        .arg("graph")
         .arg(path)
+        .arg("--format")
+        .arg(format)
         .assert()
-        .stdout(contains("valid: 1, invalid: 0"))
+        .success()
         .stdout(contains(output));
 }
+
+pub fn test_fix_dry_run(f: &str, output: &str) {
+    let path = ["tests/fixtures", f].join("/");
+    Command::cargo_bin("eipv")
+        .expect("eipv binary missing")
+        .arg("fix")
+        .arg("--dry-run")
+        .arg(path)
+        .assert()
+        .success()
+        .stdout(contains(output));
+}
+
+/// Runs `eipv` with `args` on a copy of the fixture, so it can be fixed in
+/// place, and returns the copy's contents afterwards.
+pub fn test_fix(f: &str, args: &[&str], output: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("eipv-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(f);
+    std::fs::copy(["tests/fixtures", f].join("/"), &path).unwrap();
+
+    Command::cargo_bin("eipv")
+        .expect("eipv binary missing")
+        .args(args)
+        .arg(&path)
+        .assert()
+        .stdout(contains(output));
+
+    let fixed = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    fixed
+}